@@ -1,9 +1,12 @@
 use std::fmt::{self, Display, Formatter};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     TypeError { expected: String, found: String },
     ReferenceUndefinedError { name: String },
+    DivisionByZero,
+    IntegerOverflow { method: String },
 }
 
 impl Display for Error {
@@ -15,6 +18,8 @@ impl Display for Error {
             Self::ReferenceUndefinedError { name } => {
                 write!(f, "reference by name {} was not in scope", name)
             }
+            Self::DivisionByZero => f.write_str("attempted to divide by zero"),
+            Self::IntegerOverflow { method } => write!(f, "integer overflow in {}", method),
         }
     }
 }
@@ -1,162 +1,1064 @@
 mod error;
 mod state;
+mod unwind;
 
-use crate::parser::ast::Ast;
+use crate::parser::ast::{Ast, Program, TypeExpr};
 use crate::parser::ast::{Operation, Operator};
 pub use error::{Error, Result};
-pub use state::{Value, ValueWrap};
+pub use state::{Builtin, Environment, Value, ValueWrap};
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use unwind::{Evaluation, Unwind};
 
 pub struct Interpreter {
-    /// Stack of references to values in the value tree
-    scopes: Vec<ValueWrap>,
+    /// The innermost scope, which links to the scopes enclosing it
+    environment: ValueWrap,
+
+    /// The methods of the declared instances, by the names of the method and of the type
+    /// implementing it
+    instances: HashMap<(String, String), ValueWrap>,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        let builtins = Builtin::ALL
+            .iter()
+            .map(|(name, builtin)| (name.to_string(), Value::Builtin(*builtin).wrapped()))
+            .chain(state::METHODS.iter().map(|method| {
+                (
+                    method.to_string(),
+                    Value::Method(method.to_string()).wrapped(),
+                )
+            }))
+            .collect();
+
         Self {
-            scopes: vec![Value::Scope(HashMap::new(), None).wrapped()],
+            environment: Value::Scope(builtins, None).wrapped(),
+            instances: HashMap::new(),
+        }
+    }
+
+    /// Run each statement of a program in the current scope, returning the value of the last
+    pub fn run(&mut self, program: &Program) -> Result<ValueWrap> {
+        let mut value = Value::Unit.wrapped();
+        for statement in &program.0 {
+            value = self.interpret(statement).map_err(|unwind| match unwind {
+                Unwind::Error(err) => err,
+                _ => unreachable!("the parser only allows jumps inside of loops and functions"),
+            })?;
         }
+
+        Ok(value)
     }
 
-    pub fn interpret(&mut self, ast: &Ast) -> Result<ValueWrap> {
+    fn interpret(&mut self, ast: &Ast) -> Evaluation<ValueWrap> {
         match ast {
-            Ast::Literal(lit) => Ok(Value::from(lit.clone()).wrapped()),
+            Ast::Literal(lit, _) => Ok(Value::from(lit.clone()).wrapped()),
 
-            Ast::Declaration { name, value } => {
-                let value = self.interpret(value)?;
-                self.scopes
-                    .last()
-                    .unwrap()
+            // Each declaration opens a scope for the rest of the enclosing one, so that closures
+            // declared before it don't see it
+            Ast::Declaration { name, value, .. } => {
+                // Functions may refer to themselves, so they are evaluated in their own scope.
+                // This makes their closure a reference cycle, which is never freed.
+                // Other values are evaluated first, as the names they declare stay in scope.
+                let value = match **value {
+                    Ast::Function { .. } => {
+                        self.open_scope();
+                        self.interpret(value)?
+                    }
+                    _ => {
+                        let value = self.interpret(value)?;
+                        self.open_scope();
+                        value
+                    }
+                };
+
+                self.environment
                     .borrow_mut()
                     .as_scope_mut()
-                    .unwrap()
+                    .expect("environments are scopes")
                     .0
                     .insert(name.clone(), value);
-                Ok(Value::Nil.wrapped())
+
+                Ok(Value::Unit.wrapped())
             }
 
-            Ast::Reference(name) => self
-                .scopes
-                .iter()
-                .rev()
-                .find_map(|sc| sc.borrow().as_scope().unwrap().0.get(name).cloned())
-                .ok_or(Error::ReferenceUndefinedError {
+            Ast::Assignment { name, value, .. } => {
+                let value = self.interpret(value)?;
+                let scope =
+                    self.binding_scope(name)
+                        .ok_or_else(|| Error::ReferenceUndefinedError {
+                            name: name.to_string(),
+                        })?;
+
+                // Rebind rather than mutate, as the old value may be shared with other bindings
+                scope
+                    .borrow_mut()
+                    .as_scope_mut()
+                    .expect("environments are scopes")
+                    .0
+                    .insert(name.clone(), value.clone());
+
+                Ok(value)
+            }
+
+            Ast::Reference(name, _) => self.lookup(name).ok_or_else(|| {
+                Error::ReferenceUndefinedError {
                     name: name.to_string(),
-                }),
+                }
+                .into()
+            }),
 
-            Ast::Grouping(children) => {
-                self.scopes
-                    .push(Value::Scope(HashMap::new(), None).wrapped());
-                let tail = children
-                    .iter()
-                    .map(|child| self.interpret(child))
-                    .reduce(Result::and)
-                    .expect("grouping has a tail")?;
-
-                Ok(Value::Scope(
-                    self.scopes
-                        .pop()
-                        .expect("scopes on the stack")
-                        .try_unwrap()
-                        .expect("no strong references to evaluated scope")
-                        .into_scope()
-                        .unwrap()
-                        .0,
-                    Some(tail),
-                )
-                .wrapped())
+            Ast::Grouping(statements, tail, _) => {
+                self.scoped(self.environment.clone(), HashMap::new(), |interpreter| {
+                    for statement in statements {
+                        interpreter.interpret(statement)?;
+                    }
+
+                    match tail {
+                        Some(tail) => interpreter.interpret(tail),
+                        None => Ok(Value::Unit.wrapped()),
+                    }
+                })
             }
 
-            Ast::Operation(op) => match op {
+            Ast::Operation(op, _) => match op {
                 Operation::Unary { operator, operand } => {
-                    let operand = self.interpret(operand)?.try_unwrap().unwrap();
-                    match operator {
-                        Operator::Not => match operand {
-                            Value::Bool(opd) => Ok(Value::Bool(!opd)),
-                            _ => Err(Error::TypeError {
-                                expected: "boolean".into(),
-                                found: format!("{:#?}", operand),
-                            }),
-                        },
-                        Operator::Negate => match operand {
-                            Value::Integer(num) => Ok(Value::Integer(-num)),
-                            Value::Float(num) => Ok(Value::Float(-num)),
-                            _ => Err(Error::TypeError {
-                                expected: "number (float or integer)".into(),
-                                found: format!("{:#?}", operand),
-                            }),
-                        },
-                        _ => unreachable!(),
+                    let operand = self.interpret(operand)?;
+                    if let Some(method) = operator.method() {
+                        return self.apply_method(method, vec![operand]);
+                    }
+
+                    let operand = operand.borrow().clone();
+                    match operand {
+                        Value::Bool(opd) => Ok(Value::Bool(!opd).wrapped()),
+                        _ => Err(Error::TypeError {
+                            expected: "boolean".into(),
+                            found: format!("{:#?}", operand),
+                        }
+                        .into()),
                     }
                 }
 
                 Operation::Binary { operator, operands } => {
-                    let operands = (
-                        self.interpret(&operands.0)?.try_unwrap().unwrap(),
-                        self.interpret(&operands.1)?.try_unwrap().unwrap(),
-                    );
-                    match operator {
-                        Operator::Equal => Ok(Value::Bool(operands.0 == operands.1)),
-                        Operator::NotEqual => Ok(Value::Bool(operands.0 != operands.1)),
-
-                        Operator::Greater => Ok(Value::Bool(
-                            operands.0.into_number()? > operands.1.into_number()?,
-                        )),
-                        Operator::GreaterEqual => Ok(Value::Bool(
-                            operands.0.into_number()? >= operands.1.into_number()?,
-                        )),
-                        Operator::Less => Ok(Value::Bool(
-                            operands.0.into_number()? < operands.1.into_number()?,
-                        )),
-                        Operator::LessEqual => Ok(Value::Bool(
-                            operands.0.into_number()? <= operands.1.into_number()?,
-                        )),
-
-                        Operator::Add => {
-                            Ok((operands.0.into_number()? + operands.1.into_number()?).into())
+                    let left = self.interpret(&operands.0)?;
+                    let right = self.interpret(&operands.1)?;
+                    if let Some(method) = operator.method() {
+                        let arguments = match operator {
+                            Operator::Greater | Operator::LessEqual => vec![right, left],
+                            _ => vec![left, right],
+                        };
+                        let value = self.apply_method(method, arguments)?;
+
+                        return match operator {
+                            Operator::NotEqual | Operator::GreaterEqual | Operator::LessEqual => {
+                                let value = value.borrow().clone();
+                                match value {
+                                    Value::Bool(value) => Ok(Value::Bool(!value).wrapped()),
+                                    _ => Err(Error::TypeError {
+                                        expected: "boolean".into(),
+                                        found: format!("{:#?}", value),
+                                    }
+                                    .into()),
+                                }
+                            }
+                            _ => Ok(value),
+                        };
+                    }
+
+                    let operands = (left.borrow().clone(), right.borrow().clone());
+                    match operands {
+                        (Value::Integer(start), Value::Integer(end)) => {
+                            Ok(Value::Range(start, end).wrapped())
+                        }
+                        _ => Err(Error::TypeError {
+                            expected: "integer".into(),
+                            found: format!("{:#?}", operands),
                         }
-                        Operator::Subtract => {
-                            Ok((operands.0.into_number()? - operands.1.into_number()?).into())
+                        .into()),
+                    }
+                }
+            },
+
+            Ast::Conditional {
+                binding,
+                condition,
+                consequence,
+                alternative,
+                ..
+            } => {
+                let condition = self.interpret(condition)?.borrow().clone();
+                match (binding, condition) {
+                    (None, Value::Bool(true)) => self.interpret(consequence),
+                    (Some(binding), Value::Some(value)) => {
+                        let bindings = vec![(binding.clone(), value.wrapped())]
+                            .into_iter()
+                            .collect();
+                        self.scoped(self.environment.clone(), bindings, |interpreter| {
+                            interpreter.interpret(consequence)
+                        })
+                    }
+                    (None, Value::Bool(false)) | (Some(_), Value::Nil) => match alternative {
+                        Some(alternative) => self.interpret(alternative),
+                        None => Ok(Value::Unit.wrapped()),
+                    },
+                    (binding, condition) => Err(Error::TypeError {
+                        expected: if binding.is_some() {
+                            "option"
+                        } else {
+                            "boolean"
                         }
-                        Operator::Multiply => {
-                            Ok((operands.0.into_number()? * operands.1.into_number()?).into())
+                        .into(),
+                        found: format!("{:#?}", condition),
+                    }
+                    .into()),
+                }
+            }
+
+            Ast::While {
+                label,
+                valued,
+                condition,
+                body,
+                ..
+            } => loop {
+                let condition = self.interpret(condition)?.borrow().clone();
+                match condition {
+                    Value::Bool(true) => {}
+                    Value::Bool(false) => break Ok(Self::exit(*valued, None)),
+                    _ => {
+                        break Err(Error::TypeError {
+                            expected: "boolean".into(),
+                            found: format!("{:#?}", condition),
                         }
-                        Operator::Divide => {
-                            Ok((operands.0.into_number()? / operands.1.into_number()?).into())
+                        .into())
+                    }
+                }
+
+                if let Some(value) = self.iterate(label, *valued, body)? {
+                    break Ok(value);
+                }
+            },
+
+            Ast::For {
+                label,
+                valued,
+                binding,
+                iterable,
+                body,
+                ..
+            } => {
+                let iterable = self.interpret(iterable)?.borrow().clone();
+                let items: Box<dyn Iterator<Item = Value>> = match iterable {
+                    Value::Range(start, end) => Box::new((start..end).map(Value::Integer)),
+                    Value::Str(string) => Box::new(
+                        string
+                            .chars()
+                            .map(|ch| Value::Str(ch.to_string()))
+                            .collect::<Vec<_>>()
+                            .into_iter(),
+                    ),
+                    _ => {
+                        return Err(Error::TypeError {
+                            expected: "iterable (range or string)".into(),
+                            found: format!("{:#?}", iterable),
                         }
+                        .into())
+                    }
+                };
+
+                for item in items {
+                    let bindings = vec![(binding.clone(), item.wrapped())]
+                        .into_iter()
+                        .collect();
+                    let exit = self.scoped(self.environment.clone(), bindings, |interpreter| {
+                        interpreter.iterate(label, *valued, body)
+                    });
 
-                        _ => unimplemented!(),
+                    if let Some(value) = exit? {
+                        return Ok(value);
                     }
                 }
+
+                Ok(Self::exit(*valued, None))
             }
-            .map(ValueWrap::wrapping),
 
-            Ast::Function { arguments, body } => {
-                Ok(Value::Function(arguments.clone(), *body.clone()).wrapped())
+            Ast::Break { label, value, .. } => {
+                let value = match value {
+                    Some(value) => Some(self.interpret(value)?),
+                    None => None,
+                };
+
+                Err(Unwind::Break {
+                    label: label.clone(),
+                    value,
+                })
             }
 
-            Ast::FunctionApplication { function, arguments } => {
-                let fn_val = self.interpret(function)?;
-                let fn_data = fn_val.borrow().clone().into_func().ok_or(Error::TypeError {
-                    expected: "function".into(),
-                    found: format!("{:?}", function),
-                })?;
+            Ast::Continue { label, .. } => Err(Unwind::Continue {
+                label: label.clone(),
+            }),
+
+            Ast::Return { value, .. } => {
+                let value = match value {
+                    Some(value) => self.interpret(value)?,
+                    None => Value::Unit.wrapped(),
+                };
+
+                Err(Unwind::Return { value })
+            }
+
+            Ast::Function {
+                arguments, body, ..
+            } => Ok(Value::Closure(
+                arguments.iter().map(|param| param.name.clone()).collect(),
+                *body.clone(),
+                Environment(self.environment.clone()),
+            )
+            .wrapped()),
+
+            Ast::FunctionApplication {
+                function,
+                arguments,
+                ..
+            } => {
+                let fn_val = self.interpret(function)?.borrow().clone();
 
                 let mut interp_args = Vec::new();
                 for raw in arguments {
                     interp_args.push(self.interpret(raw)?);
                 }
 
-                self.scopes.push(Value::Scope(
-                    fn_data.0.clone().into_iter().zip(interp_args).collect(),
+                self.call(fn_val, interp_args)
+            }
+
+            Ast::Interface { methods, .. } => {
+                let bindings = methods
+                    .iter()
+                    .map(|signature| {
+                        let method = Value::Method(signature.name.clone()).wrapped();
+                        (signature.name.clone(), method)
+                    })
+                    .collect();
+                self.environment = Value::Scope(bindings, Some(self.environment.clone())).wrapped();
+
+                Ok(Value::Unit.wrapped())
+            }
+
+            Ast::Instance { ty, methods, .. } => {
+                let type_name = match ty {
+                    TypeExpr::Named { name, .. } => name,
+                    TypeExpr::Function { .. } => {
+                        unreachable!("instances are only checked for named types")
+                    }
+                };
+
+                for method in methods {
+                    if let Ast::Declaration { name, value, .. } = method {
+                        let implementation = self.interpret(value)?;
+                        self.instances
+                            .insert((name.clone(), type_name.clone()), implementation);
+                    }
+                }
+
+                Ok(Value::Unit.wrapped())
+            }
+
+            Ast::Error => unreachable!("syntax errors are reported before interpretation"),
+        }
+    }
+
+    /// Apply a function to the values of its arguments
+    fn call(&mut self, function: Value, arguments: Vec<ValueWrap>) -> Evaluation<ValueWrap> {
+        let (parameters, body, environment) = match function {
+            Value::Closure(parameters, body, environment) => (parameters, body, environment),
+            Value::Builtin(builtin) => {
+                let arguments = arguments.iter().map(|arg| arg.borrow().clone());
+                return Ok(builtin.apply(arguments.collect()).wrapped());
+            }
+            Value::Method(method) => return self.apply_method(&method, arguments),
+            _ => {
+                return Err(Error::TypeError {
+                    expected: "function".into(),
+                    found: format!("{:?}", function),
+                }
+                .into())
+            }
+        };
+
+        // The body is evaluated in the closure's environment rather than the caller's
+        let bindings = parameters.into_iter().zip(arguments).collect();
+        let returned = self.scoped(environment.0, bindings, |interpreter| {
+            interpreter.interpret(&body)
+        });
+
+        match returned {
+            Err(Unwind::Return { value }) => Ok(value),
+            returned => returned,
+        }
+    }
+
+    /// Apply a method by the instance for the type of its first argument, which is either declared
+    /// or builtin
+    fn apply_method(&mut self, method: &str, arguments: Vec<ValueWrap>) -> Evaluation<ValueWrap> {
+        let type_name = arguments[0].borrow().type_name();
+        let implementation = type_name
+            .and_then(|type_name| {
+                self.instances
+                    .get(&(method.to_string(), type_name.to_string()))
+            })
+            .map(|implementation| implementation.borrow().clone());
+
+        match implementation {
+            Some(implementation) => self.call(implementation, arguments),
+            None => self.apply_builtin_method(method, arguments),
+        }
+    }
+
+    /// Apply a method of a builtin interface by the builtin instance for its arguments
+    ///
+    /// Options compare and show their values by the instances of their types.
+    fn apply_builtin_method(
+        &mut self,
+        method: &str,
+        arguments: Vec<ValueWrap>,
+    ) -> Evaluation<ValueWrap> {
+        let mut arguments = arguments.iter().map(|arg| arg.borrow().clone());
+        let value = match (method, arguments.next().unwrap(), arguments.next()) {
+            ("eq", Value::Some(a), Some(Value::Some(b))) => {
+                return self.apply_method("eq", vec![a.wrapped(), b.wrapped()])
+            }
+            ("eq", a, Some(b)) => Value::Bool(a == b),
+            ("lt", a, Some(b)) => Value::Bool(a.compare(b)? == Some(Ordering::Less)),
+
+            ("add", Value::Str(a), Some(Value::Str(b))) => Value::Str(a + &b),
+            ("div", Value::Integer(_), Some(Value::Integer(0))) => {
+                return Err(Error::DivisionByZero.into())
+            }
+            ("add", Value::Integer(a), Some(Value::Integer(b))) => {
+                Value::Integer(checked(method, a.checked_add(b))?)
+            }
+            ("sub", Value::Integer(a), Some(Value::Integer(b))) => {
+                Value::Integer(checked(method, a.checked_sub(b))?)
+            }
+            ("mul", Value::Integer(a), Some(Value::Integer(b))) => {
+                Value::Integer(checked(method, a.checked_mul(b))?)
+            }
+            ("div", Value::Integer(a), Some(Value::Integer(b))) => {
+                Value::Integer(checked(method, a.checked_div(b))?)
+            }
+            ("add", a, Some(b)) => (a.into_number()? + b.into_number()?).into(),
+            ("sub", a, Some(b)) => (a.into_number()? - b.into_number()?).into(),
+            ("mul", a, Some(b)) => (a.into_number()? * b.into_number()?).into(),
+            ("div", a, Some(b)) => (a.into_number()? / b.into_number()?).into(),
+            ("neg", Value::Integer(num), None) => {
+                Value::Integer(checked(method, num.checked_neg())?)
+            }
+            ("neg", Value::Float(num), None) => Value::Float(-num),
+
+            ("show", Value::Some(value), None) => {
+                let shown = self.apply_method("show", vec![value.wrapped()])?;
+                let shown = shown.borrow().show();
+                Value::Str(format!("some({})", shown))
+            }
+            ("show", value, None) => Value::Str(value.show()),
+
+            (method, value, _) => {
+                return Err(Error::TypeError {
+                    expected: format!("a type implementing {}", method),
+                    found: format!("{:#?}", value),
+                }
+                .into())
+            }
+        };
+
+        Ok(value.wrapped())
+    }
+
+    /// Look up the value bound to a name in the innermost scope binding it
+    fn lookup(&self, name: &str) -> Option<ValueWrap> {
+        self.binding_scope(name)?
+            .borrow()
+            .as_scope()
+            .expect("environments are scopes")
+            .0
+            .get(name)
+            .cloned()
+    }
+
+    /// Find the innermost scope binding a name
+    fn binding_scope(&self, name: &str) -> Option<ValueWrap> {
+        let mut scope = self.environment.clone();
+
+        loop {
+            let enclosing = {
+                let scope = scope.borrow();
+                let (bindings, enclosing) = scope.as_scope().expect("environments are scopes");
+                if bindings.contains_key(name) {
                     None
-                ).wrapped());
-                let returned = self.interpret(&fn_data.1)?;
-                self.scopes.pop().expect("scopes on the stack");
+                } else {
+                    Some(enclosing.clone()?)
+                }
+            };
 
-                Ok(returned)
+            match enclosing {
+                Some(enclosing) => scope = enclosing,
+                None => return Some(scope),
             }
         }
     }
+
+    /// Evaluate in a new scope with the given bindings, enclosed by the given environment
+    fn scoped<T>(
+        &mut self,
+        environment: ValueWrap,
+        bindings: HashMap<String, ValueWrap>,
+        evaluate: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let scope = Value::Scope(bindings, Some(environment)).wrapped();
+        let enclosing = std::mem::replace(&mut self.environment, scope);
+        let res = evaluate(self);
+        self.environment = enclosing;
+
+        res
+    }
+
+    /// Open an empty scope for the rest of the current one
+    fn open_scope(&mut self) {
+        self.environment = Value::Scope(HashMap::new(), Some(self.environment.clone())).wrapped();
+    }
+
+    /// Run one iteration of a loop's body, returning the loop's value if it was broken out of
+    fn iterate(
+        &mut self,
+        label: &Option<String>,
+        valued: bool,
+        body: &Ast,
+    ) -> Evaluation<Option<ValueWrap>> {
+        match self.interpret(body) {
+            Ok(_) => Ok(None),
+            Err(Unwind::Break {
+                label: target,
+                value,
+            }) if Unwind::targets(&target, label) => Ok(Some(Self::exit(valued, value))),
+            Err(Unwind::Continue { label: target }) if Unwind::targets(&target, label) => Ok(None),
+            Err(unwind) => Err(unwind),
+        }
+    }
+
+    /// The value of a loop once exited, given the value it was broken out of with if any
+    ///
+    /// A valued loop is an option of that value, and any other loop is unit.
+    fn exit(valued: bool, value: Option<ValueWrap>) -> ValueWrap {
+        match value {
+            _ if !valued => Value::Unit.wrapped(),
+            Some(value) => Value::Some(Box::new(value.borrow().clone())).wrapped(),
+            None => Value::Nil.wrapped(),
+        }
+    }
+}
+
+/// Report an overflow of an integer operation
+fn checked(method: &str, result: Option<isize>) -> Result<isize> {
+    result.ok_or_else(|| Error::IntegerOverflow {
+        method: method.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Analyzer;
+    use crate::parser::Parser;
+    use crate::source::SourceMap;
+    use crate::typeck::Checker;
+
+    fn parse(source: &str) -> Program {
+        let file = SourceMap::new().add_repl_input(source.into());
+        let tokens = Analyzer::new(source.chars(), file).map(|tk| tk.unwrap());
+        let (program, errors) = Parser::new(tokens).parse();
+        assert_eq!(errors, []);
+
+        program
+    }
+
+    fn run(interpreter: &mut Interpreter, source: &str) -> Result<Value> {
+        interpreter
+            .run(&parse(source))
+            .map(|value| value.borrow().clone())
+    }
+
+    #[test]
+    fn declarations_persist_between_inputs() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(run(&mut interpreter, "let x = 1"), Ok(Value::Unit));
+        assert_eq!(run(&mut interpreter, "x + 1"), Ok(Value::Integer(2)));
+
+        run(&mut interpreter, "let y = x * 3").unwrap();
+        assert_eq!(run(&mut interpreter, "y"), Ok(Value::Integer(3)));
+    }
+
+    #[test]
+    fn evaluates_blocks() {
+        let mut interpreter = Interpreter::new();
+
+        for (source, value) in &[
+            ("{}", Value::Unit),
+            ("{ let x = 1; x }", Value::Integer(1)),
+            ("{ let x = 1; x; }", Value::Unit),
+            ("{ ;; 1 ;; }", Value::Unit),
+            ("{ ; 2 }", Value::Integer(2)),
+            ("{ { 1 } 2 }", Value::Integer(2)),
+            ("(1 + 2) * 3", Value::Integer(9)),
+        ] {
+            assert_eq!(
+                run(&mut interpreter, source).as_ref(),
+                Ok(value),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn blocks_scope_declarations() {
+        let mut interpreter = Interpreter::new();
+        let undefined = |name: &str| Err(Error::ReferenceUndefinedError { name: name.into() });
+
+        assert_eq!(
+            run(&mut interpreter, "{ let inner = 1; }; inner"),
+            undefined("inner")
+        );
+        assert_eq!(
+            run(&mut interpreter, "{ let leaked = 1; missing }"),
+            undefined("missing")
+        );
+        assert_eq!(run(&mut interpreter, "leaked"), undefined("leaked"));
+    }
+
+    #[test]
+    fn evaluates_conditionals() {
+        let mut interpreter = Interpreter::new();
+
+        for (source, value) in &[
+            ("if true { 1 } else { 2 }", Value::Integer(1)),
+            ("if 1 > 2 { 1 } else { 2 }", Value::Integer(2)),
+            ("if false { 1 }", Value::Unit),
+            (
+                "if false { 1 } else if true { 2 } else { 3 }",
+                Value::Integer(2),
+            ),
+            (
+                "let x = if !true { 1 } else { 2 }; x * 2",
+                Value::Integer(4),
+            ),
+            ("if true { 1 } 2", Value::Integer(2)),
+            ("if true { 1 } -2", Value::Integer(-2)),
+            (
+                "fn f(x) { if x < 0 { return 0 }\n(x + 1) * 2 }; f(1) + f(-1)",
+                Value::Integer(4),
+            ),
+            ("if let x = some(1) { x + 1 } else { 0 }", Value::Integer(2)),
+            ("if let x = nil { x } else { 0 }", Value::Integer(0)),
+            (
+                "if let x = some(nil) { if let y = x { y } else { 1 } } else { 2 }",
+                Value::Integer(1),
+            ),
+            ("let y = 5; if let y = some(1) { y; }; y", Value::Integer(5)),
+            ("some(1) == some(1)", Value::Bool(true)),
+            (
+                "show(some(nil)) + show({})",
+                Value::Str("some(nil)()".into()),
+            ),
+        ] {
+            assert_eq!(
+                run(&mut interpreter, source).as_ref(),
+                Ok(value),
+                "{}",
+                source
+            );
+        }
+
+        assert_eq!(
+            run(&mut interpreter, "if 1 { 2 }"),
+            Err(Error::TypeError {
+                expected: "boolean".into(),
+                found: format!("{:#?}", Value::Integer(1)),
+            })
+        );
+    }
+
+    #[test]
+    fn reports_arithmetic_errors() {
+        let mut interpreter = Interpreter::new();
+        let overflow = |method: &str| {
+            Err(Error::IntegerOverflow {
+                method: method.into(),
+            })
+        };
+
+        assert_eq!(run(&mut interpreter, "1 / 0"), Err(Error::DivisionByZero));
+        assert_eq!(
+            run(&mut interpreter, "1.0 / 0.0"),
+            Ok(Value::Float(f64::INFINITY))
+        );
+        assert_eq!(
+            run(&mut interpreter, "9223372036854775807 + 1"),
+            overflow("add")
+        );
+        assert_eq!(
+            run(&mut interpreter, "-9223372036854775807 - 2"),
+            overflow("sub")
+        );
+        assert_eq!(
+            run(&mut interpreter, "9223372036854775807 * 2"),
+            overflow("mul")
+        );
+        assert_eq!(
+            run(&mut interpreter, "(-9223372036854775807 - 1) / -1"),
+            overflow("div")
+        );
+        assert_eq!(
+            run(&mut interpreter, "-(-9223372036854775807 - 1)"),
+            overflow("neg")
+        );
+    }
+
+    #[test]
+    fn evaluates_loops() {
+        let mut interpreter = Interpreter::new();
+
+        for (source, value) in &[
+            ("0..3", Value::Range(0, 3)),
+            ("while false {}", Value::Unit),
+            ("while true { break }", Value::Unit),
+            (
+                "while true { break 5 }",
+                Value::Some(Box::new(Value::Integer(5))),
+            ),
+            (
+                "for i in 0..5 { if i == 3 { break i * 10 } }",
+                Value::Some(Box::new(Value::Integer(30))),
+            ),
+            ("for i in 0..3 { if i == 5 { break i } }", Value::Nil),
+            ("for i in 0..3 { continue; break 1 }", Value::Nil),
+            (
+                "for i in 0..3 { if i == 0 { break } else { break 1 } }",
+                Value::Nil,
+            ),
+            (
+                r#"for c in "abc" { if c == "b" { break c } }"#,
+                Value::Some(Box::new(Value::Str("b".into()))),
+            ),
+            (
+                "'outer: for i in 0..3 {
+                    for j in 0..3 {
+                        if j == 1 { continue 'outer }
+                        if i == 2 { break 'outer i * 10 + j }
+                    }
+                }",
+                Value::Some(Box::new(Value::Integer(20))),
+            ),
+            (
+                "'a: while true { while true { break 'a 1 }; break 2 }",
+                Value::Some(Box::new(Value::Integer(1))),
+            ),
+            (
+                "'a: while true { while true { break 'a 1 } }",
+                Value::Some(Box::new(Value::Integer(1))),
+            ),
+        ] {
+            assert_eq!(
+                run(&mut interpreter, source).as_ref(),
+                Ok(value),
+                "{}",
+                source
+            );
+        }
+
+        assert!(matches!(
+            run(&mut interpreter, "for x in 1 {}"),
+            Err(Error::TypeError { .. })
+        ));
+        assert!(matches!(
+            run(&mut interpreter, "while 1 {}"),
+            Err(Error::TypeError { .. })
+        ));
+    }
+
+    #[test]
+    fn returns_from_functions() {
+        let mut interpreter = Interpreter::new();
+        run(
+            &mut interpreter,
+            "fn sign(x) {
+                if x < 0 { return -1 }
+                if x == 0 { return 0 }
+                1
+            };
+            fn nothing() { return; 1 };
+            fn first_over(n) { for i in 0..100 { if i > n { return i } } }",
+        )
+        .unwrap();
+
+        for (source, value) in &[
+            ("sign(-5)", Value::Integer(-1)),
+            ("sign(0)", Value::Integer(0)),
+            ("sign(3)", Value::Integer(1)),
+            ("nothing()", Value::Unit),
+            ("first_over(5)", Value::Integer(6)),
+        ] {
+            assert_eq!(
+                run(&mut interpreter, source).as_ref(),
+                Ok(value),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn applies_anonymous_functions() {
+        let mut interpreter = Interpreter::new();
+
+        for (source, value) in &[
+            ("let inc = |x| x + 1; inc(2)", Value::Integer(3)),
+            ("(fn(x) x * 2)(4)", Value::Integer(8)),
+            ("(|| 5)()", Value::Integer(5)),
+            (
+                "fn twice(f, x) f(f(x)); twice(|x| x * 3, 2)",
+                Value::Integer(18),
+            ),
+            ("fn make() |x| x + 1; make()(1)", Value::Integer(2)),
+        ] {
+            assert_eq!(
+                run(&mut interpreter, source).as_ref(),
+                Ok(value),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn operates_on_strings_and_builtins() {
+        let mut interpreter = Interpreter::new();
+
+        for (source, value) in &[
+            ("\"ab\" + \"c\"", Value::Str("abc".into())),
+            ("\"ab\" < \"b\"", Value::Bool(true)),
+            ("\"b\" >= \"b\"", Value::Bool(true)),
+            ("1.5 > 2", Value::Bool(false)),
+            ("show(1) + show(2.5)", Value::Str("12.5".into())),
+            ("show(0..3)", Value::Str("0..3".into())),
+            ("show(nil) + show(true)", Value::Str("niltrue".into())),
+            ("let s = show; s(\"a\")", Value::Str("a".into())),
+        ] {
+            assert_eq!(
+                run(&mut interpreter, source).as_ref(),
+                Ok(value),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn dispatches_operators_through_instances() {
+        let mut interpreter = Interpreter::new();
+        run(
+            &mut interpreter,
+            "instance Add for Bool { fn add(a, b) if a { a } else { b } }
+            instance Ord for Bool { fn lt(a, b) if a { false } else { b } }
+            interface Describe { fn describe(x: Self) -> String }
+            instance Describe for Integer { fn describe(n) \"integer \" + show(n) }",
+        )
+        .unwrap();
+
+        for (source, value) in &[
+            ("true + false", Value::Bool(true)),
+            ("false + false", Value::Bool(false)),
+            ("false < true", Value::Bool(true)),
+            ("true > false", Value::Bool(true)),
+            ("true <= true", Value::Bool(true)),
+            ("false >= true", Value::Bool(false)),
+            ("describe(2)", Value::Str("integer 2".into())),
+            ("let d = describe; d(-1)", Value::Str("integer -1".into())),
+            ("some(1) == some(1)", Value::Bool(true)),
+            ("some(1) != some(2)", Value::Bool(true)),
+            ("nil == some(1)", Value::Bool(false)),
+            (
+                "show(some(some(\"a\")))",
+                Value::Str("some(some(a))".into()),
+            ),
+        ] {
+            assert_eq!(
+                run(&mut interpreter, source).as_ref(),
+                Ok(value),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn closures_capture_their_environment() {
+        let mut interpreter = Interpreter::new();
+        run(
+            &mut interpreter,
+            "fn adder(n) |x| x + n;
+            fn fact(n) if n < 2 { 1 } else { n * fact(n - 1) };
+            fn peek() secret",
+        )
+        .unwrap();
+
+        assert_eq!(run(&mut interpreter, "adder(2)(3)"), Ok(Value::Integer(5)));
+        assert_eq!(
+            run(
+                &mut interpreter,
+                "let add3 = adder(3); let n = 100; add3(1)"
+            ),
+            Ok(Value::Integer(4))
+        );
+        assert_eq!(run(&mut interpreter, "fact(5)"), Ok(Value::Integer(120)));
+
+        // Callees can't see their caller's bindings
+        assert_eq!(
+            run(&mut interpreter, "{ let secret = 1; peek() }"),
+            Err(Error::ReferenceUndefinedError {
+                name: "secret".into()
+            })
+        );
+
+        // A closure in its own environment is printed without recursing into it
+        let fact = run(&mut interpreter, "fact").unwrap();
+        assert!(format!("{:?}", fact).contains("Environment(..)"));
+    }
+
+    #[test]
+    fn closures_only_see_earlier_declarations() {
+        let mut interpreter = Interpreter::new();
+
+        for (source, value) in &[
+            (
+                "let a = 1; { fn g() a; let a = true; g() + 1 }",
+                Value::Integer(2),
+            ),
+            (
+                "let x = 1; fn f() x + 1; let x = \"a\"; f()",
+                Value::Integer(2),
+            ),
+            (
+                "let x = 1; fn f() x; let x = \"a\"; x + show(f())",
+                Value::Str("a1".into()),
+            ),
+            // Assignments are still seen, as they don't declare a new binding
+            ("let y = 1; fn g() y; y = 2; g()", Value::Integer(2)),
+            (
+                "fn even(n) if n == 0 { true } else { !even(n - 1) }; even(4)",
+                Value::Bool(true),
+            ),
+        ] {
+            assert_eq!(
+                run(&mut interpreter, source).as_ref(),
+                Ok(value),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn assigns_to_nearest_binding() {
+        let mut interpreter = Interpreter::new();
+
+        for (source, value) in &[
+            ("let x = 0; x = 5", Value::Integer(5)),
+            ("let a = 0; let b = 0; a = b = 3; a + b", Value::Integer(6)),
+            ("let a = 1; let b = a; b = 2; a", Value::Integer(1)),
+            ("let y = 1; { let y = 2; y = 3 }; y", Value::Integer(1)),
+            ("let z = 1; { z = 2 }; z", Value::Integer(2)),
+            (
+                "let i = 0; let sum = 0; while i < 5 { sum = sum + i; i = i + 1 }; sum",
+                Value::Integer(10),
+            ),
+            (
+                "fn counter() { let n = 0; || { n = n + 1; n } };
+                let count = counter();
+                count(); count()",
+                Value::Integer(2),
+            ),
+        ] {
+            assert_eq!(
+                run(&mut interpreter, source).as_ref(),
+                Ok(value),
+                "{}",
+                source
+            );
+        }
+
+        assert_eq!(
+            run(&mut interpreter, "unbound = 1"),
+            Err(Error::ReferenceUndefinedError {
+                name: "unbound".into()
+            })
+        );
+    }
+
+    #[test]
+    fn runs_declarations_in_declared_values() {
+        for (source, ty, value) in &[
+            ("let a = let b = 1; b", "Integer", Value::Integer(1)),
+            ("let a = let b = 1; a", "Unit", Value::Unit),
+            (
+                "let a = { let b = 2; b }; let c = let d = a; d + a",
+                "Integer",
+                Value::Integer(4),
+            ),
+        ] {
+            // Whatever the checker accepts must also run
+            let program = parse(source);
+            assert_eq!(
+                Checker::new().check(&program).map(|ty| ty.to_string()),
+                Ok(ty.to_string()),
+                "{}",
+                source
+            );
+            assert_eq!(
+                Interpreter::new()
+                    .run(&program)
+                    .map(|value| value.borrow().clone()),
+                Ok(value.clone()),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn functions_drop_their_scope_on_error() {
+        let mut interpreter = Interpreter::new();
+        run(&mut interpreter, "fn fail(leaked) missing").unwrap();
+
+        assert!(run(&mut interpreter, "fail(1)").is_err());
+        assert_eq!(
+            run(&mut interpreter, "leaked"),
+            Err(Error::ReferenceUndefinedError {
+                name: "leaked".into()
+            })
+        );
+    }
+
+    #[test]
+    fn runs_programs_in_global_scope() {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(
+            run(&mut interpreter, "let a = 1; let b = a + 1; b * 2"),
+            Ok(Value::Integer(4))
+        );
+        assert_eq!(run(&mut interpreter, "a + b;"), Ok(Value::Integer(3)));
+        assert_eq!(run(&mut interpreter, ""), Ok(Value::Unit));
+    }
 }
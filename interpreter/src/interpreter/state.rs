@@ -11,14 +11,33 @@ use std::rc::Rc;
 /// A runtime Smoke value
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
+    /// The value of statements, and expressions which have no other value
+    Unit,
+
+    /// An absent optional value
     Nil,
+
+    /// A present optional value
+    Some(Box<Value>),
+
     Bool(bool),
     Integer(isize),
     Float(f64),
     Str(String),
 
-    Function(Vec<String>, Ast),
+    /// A function together with the scope it was defined in
+    Closure(Vec<String>, Ast, Environment),
+
+    /// A function provided by the interpreter
+    Builtin(Builtin),
+
+    /// A method of an interface, applying the instance for the type of its first argument
+    Method(String),
 
+    /// A half-open range of integers
+    Range(isize, isize),
+
+    /// The bindings of a scope, and the scope enclosing it if any
     Scope(HashMap<String, ValueWrap>, Option<ValueWrap>),
 }
 
@@ -43,14 +62,14 @@ impl Value {
     extract_variant_method!(as_int(&self) { Self::Integer as (a): (&isize) });
     extract_variant_method!(as_float(&self) { Self::Float as (a): (&f64) });
     extract_variant_method!(as_str(&self) { Self::Str as (a): (&str) });
-    extract_variant_method!(as_func(&self) { Self::Function as (a, b): (&Vec<String>, &Ast) });
+    extract_variant_method!(as_closure(&self) { Self::Closure as (a, b, c): (&Vec<String>, &Ast, &Environment) });
     extract_variant_method!(as_scope(&self) { Self::Scope as (a, b): (&HashMap<String, ValueWrap>, &Option<ValueWrap>) });
 
     extract_variant_method!(into_bool(self) { Self::Bool as (a): (bool) });
     extract_variant_method!(into_int(self) { Self::Integer as (a): (isize) });
     extract_variant_method!(into_float(self) { Self::Float as (a): (f64) });
     extract_variant_method!(into_str(self) { Self::Str as (a): (String) });
-    extract_variant_method!(into_func(self) { Self::Function as (a, b): (Vec<String>, Ast) });
+    extract_variant_method!(into_closure(self) { Self::Closure as (a, b, c): (Vec<String>, Ast, Environment) });
     extract_variant_method!(into_scope(self) { Self::Scope as (a, b): (HashMap<String, ValueWrap>, Option<ValueWrap>) });
 
     pub fn as_scope_mut(
@@ -62,6 +81,44 @@ impl Value {
         }
     }
 
+    /// Order two numbers or two strings
+    pub fn compare(self, other: Self) -> Result<Option<cmp::Ordering>> {
+        match (self, other) {
+            (Self::Str(a), Self::Str(b)) => Ok(a.partial_cmp(&b)),
+            (a, b) => Ok(a.into_number()?.partial_cmp(&b.into_number()?)),
+        }
+    }
+
+    /// The name of the type of a value which instances may be declared for, if any
+    pub fn type_name(&self) -> Option<&'static str> {
+        match self {
+            Self::Unit => Some("Unit"),
+            Self::Nil | Self::Some(_) => Some("Option"),
+            Self::Bool(_) => Some("Bool"),
+            Self::Integer(_) => Some("Integer"),
+            Self::Float(_) => Some("Float"),
+            Self::Str(_) => Some("String"),
+            Self::Range(..) => Some("Range"),
+            Self::Closure(..) | Self::Builtin(_) | Self::Method(_) | Self::Scope(..) => None,
+        }
+    }
+
+    /// The text of a value, as given by the builtin instances of `Show`
+    pub fn show(&self) -> String {
+        match self {
+            Self::Unit => "()".into(),
+            Self::Nil => "nil".into(),
+            Self::Some(val) => format!("some({})", val.show()),
+            Self::Bool(val) => val.to_string(),
+            Self::Integer(val) => val.to_string(),
+            Self::Float(val) => val.to_string(),
+            Self::Str(val) => val.clone(),
+            Self::Range(start, end) => format!("{}..{}", start, end),
+            Self::Closure(..) | Self::Builtin(_) | Self::Method(_) => "<fn>".into(),
+            Self::Scope(..) => "<scope>".into(),
+        }
+    }
+
     pub fn into_number(self) -> Result<NumberValue> {
         match self {
             Self::Integer(num) => Ok(num.into()),
@@ -101,6 +158,26 @@ from_host_val_impl!(From<isize> for Value::Integer);
 from_host_val_impl!(From<f64> for Value::Float);
 from_host_val_impl!(From<String> for Value::Str);
 
+/// A function provided by the interpreter, bound in the global scope
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Builtin {
+    /// Make a value optional
+    Some,
+}
+
+impl Builtin {
+    pub const ALL: &'static [(&'static str, Self)] = &[("some", Self::Some)];
+
+    pub fn apply(self, mut arguments: Vec<Value>) -> Value {
+        match self {
+            Self::Some => Value::Some(Box::new(arguments.remove(0))),
+        }
+    }
+}
+
+/// The methods of the builtin interfaces, which the builtin types implement natively
+pub const METHODS: &[&str] = &["eq", "lt", "add", "sub", "mul", "div", "neg", "show"];
+
 /// A wrapper around [`Value`] providing reference counting and shared mutability
 #[derive(Clone, PartialEq)]
 pub struct ValueWrap {
@@ -114,6 +191,7 @@ impl ValueWrap {
         }
     }
 
+    #[allow(dead_code)]
     pub fn try_unwrap(self) -> std::result::Result<Value, Self> {
         Rc::try_unwrap(self.inner)
             .map_err(|rc| Self { inner: rc })
@@ -136,6 +214,25 @@ impl fmt::Debug for ValueWrap {
     }
 }
 
+/// A reference to the scope in which a closure was defined
+///
+/// A closure's environment usually contains the closure itself, so environments are compared
+/// by identity and aren't printed.
+#[derive(Clone)]
+pub struct Environment(pub ValueWrap);
+
+impl PartialEq for Environment {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0.inner, &other.0.inner)
+    }
+}
+
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Environment(..)")
+    }
+}
+
 /// A subset of values which can be ordered
 #[derive(Debug, Clone, PartialEq)]
 pub enum NumberValue {
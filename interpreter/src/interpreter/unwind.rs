@@ -0,0 +1,39 @@
+use super::{Error, ValueWrap};
+
+/// A reason for evaluation to stop before producing a value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Unwind {
+    Error(Error),
+
+    /// An exit from a loop, carrying the value it was broken out of with if any
+    Break {
+        label: Option<String>,
+        value: Option<ValueWrap>,
+    },
+
+    /// A skip to the next iteration of a loop
+    Continue {
+        label: Option<String>,
+    },
+
+    /// An exit from a function, carrying its return value
+    Return {
+        value: ValueWrap,
+    },
+}
+
+impl Unwind {
+    /// Whether a jump targets the loop with the given label, unlabelled jumps targeting any loop
+    pub fn targets(target: &Option<String>, label: &Option<String>) -> bool {
+        target.is_none() || target == label
+    }
+}
+
+impl From<Error> for Unwind {
+    fn from(err: Error) -> Self {
+        Self::Error(err)
+    }
+}
+
+/// The result of evaluating a syntax tree
+pub type Evaluation<T> = std::result::Result<T, Unwind>;
@@ -1,164 +1,340 @@
 use super::token::Token;
+use super::{Analyzer, Error, Result};
+use crate::source::Span;
+use unicode_xid::UnicodeXID;
+
+/// Look up the keyword or keyword-like literal corresponding to an identifier, if any
+pub fn keyword(ident: &str) -> Option<Token> {
+    match ident {
+        "fn" => Some(Token::Function),
+        "return" => Some(Token::Return),
+        "let" => Some(Token::Let),
+        "if" => Some(Token::If),
+        "else" => Some(Token::Else),
+        "for" => Some(Token::For),
+        "while" => Some(Token::While),
+        "in" => Some(Token::In),
+        "break" => Some(Token::Break),
+        "continue" => Some(Token::Continue),
+        "interface" => Some(Token::Interface),
+        "instance" => Some(Token::Instance),
+
+        "true" => Some(Token::Bool(true)),
+        "false" => Some(Token::Bool(false)),
+        "nil" => Some(Token::Nil),
+
+        _ => None,
+    }
+}
 
-pub trait Parse: Sized {
-    fn parse_from<I>(input: &mut I) -> Vec<(String, Self)>
-    where
-        I: Iterator<Item = char> + Clone;
+/// Whether a character may start an identifier
+///
+/// Identifiers follow the default syntax of [UAX #31](https://www.unicode.org/reports/tr31/),
+/// with `_` additionally allowed at the start: an `XID_Start` character or `_`, followed by any
+/// number of `XID_Continue` characters. Identifiers are compared by code point, without
+/// normalization.
+pub fn is_identifier_start(ch: char) -> bool {
+    ch == '_' || ch.is_xid_start()
 }
 
-impl Parse for Token {
-    fn parse_from<I>(input: &mut I) -> Vec<(String, Self)>
-    where
-        I: Iterator<Item = char> + Clone,
-    {
-        let mut ret = Vec::new();
-
-        use token_parsers::TokenParser;
-        macro_rules! use_parser {
-            ($parser:ty) => {
-                ret.append(&mut <$parser>::parse(&mut input.clone()))
+/// Whether a character may continue an identifier (see [`is_identifier_start`])
+pub fn is_identifier_continue(ch: char) -> bool {
+    ch.is_xid_continue()
+}
+
+impl<S> Analyzer<S>
+where
+    S: Iterator<Item = char>,
+{
+    /// Scan a single token, consuming the longest lexeme which forms a token
+    ///
+    /// The source must not be exhausted.
+    pub(super) fn scan_token(&mut self) -> Result<Token> {
+        macro_rules! one_or_two {
+            ($single:expr, $second:expr => $double:expr) => {
+                if self.advance_if(|ch| ch == $second).is_some() {
+                    $double
+                } else {
+                    $single
+                }
             };
+        }
+
+        Ok(match self.advance().expect("source was exhausted") {
+            // Braces
+            '(' => Token::ParenLeft,
+            ')' => Token::ParenRight,
+            '{' => Token::CurlyLeft,
+            '}' => Token::CurlyRight,
+            '[' => Token::SquareLeft,
+            ']' => Token::SquareRight,
 
-            ($( $parser:ty ),*,) => {
-                $( use_parser!($parser); )*
+            // Operators
+            ',' => Token::Comma,
+            '.' => one_or_two!(Token::Dot, '.' => Token::DotDot),
+            '-' => one_or_two!(Token::Minus, '>' => Token::Arrow),
+            '+' => Token::Plus,
+            '/' => Token::Slash,
+            '*' => Token::Star,
+            '|' => Token::Pipe,
+            '=' => one_or_two!(Token::Equal, '=' => Token::EqualEqual),
+            '!' => one_or_two!(Token::Bang, '=' => Token::BangEqual),
+            '>' => one_or_two!(Token::Greater, '=' => Token::GreaterEqual),
+            '<' => one_or_two!(Token::Less, '=' => Token::LessEqual),
+
+            ';' => Token::Semicolon,
+            ':' => Token::Colon,
+            '?' => Token::Question,
+
+            // Loop labels
+            '\'' if matches!(self.peek(), Some(ch) if is_identifier_start(ch)) => {
+                while self.advance_if(is_identifier_continue).is_some() {}
+
+                Token::Label(self.lexeme[1..].into())
             }
-        }
 
-        // NOTE: This order is important, since parser results are appended in this order
-        use_parser![
-            token_parsers::Static,
-            token_parsers::Identifier,
-            token_parsers::Str,
-            token_parsers::Integer,
-            token_parsers::Float,
-        ];
+            // Literals
+            '"' => return self.scan_string(),
+            'r' if matches!(self.peek(), Some('"' | '#')) => return self.scan_raw_string(),
+            ch if ch.is_ascii_digit() => return self.scan_number(),
 
-        ret
-    }
-}
+            ch if is_identifier_start(ch) => {
+                while self.advance_if(is_identifier_continue).is_some() {}
 
-mod token_parsers {
-    use crate::lexer::token::Token::{self, *};
+                keyword(&self.lexeme).unwrap_or_else(|| Token::Identifier(self.lexeme.clone()))
+            }
 
-    pub trait TokenParser {
-        fn parse<I: Iterator<Item = char> + Clone>(input: &mut I) -> Vec<(String, Token)>;
+            character => {
+                return Err(Error::UnrecognizedCharacter {
+                    character,
+                    span: self.lexeme_span(),
+                })
+            }
+        })
     }
 
-    macro_rules! decl_parser {
-        ($type:ty, $name:ident : |$input:ident| $body:expr) => {
-            pub struct $name;
-            impl TokenParser for $name {
-                fn parse<I: Iterator<Item = char> + Clone>($input: &mut I) -> Vec<(String, Token)> { $body }
-            }
+    /// Scan the remainder of a number literal after its first digit
+    ///
+    /// Integers may be written in hexadecimal (`0x`), octal (`0o`) or binary (`0b`), and
+    /// decimal integers and floats may contain `_` digit separators. Floats may have an exponent.
+    fn scan_number(&mut self) -> Result<Token> {
+        let next = self.peek();
+        let radix = match (self.lexeme.as_str(), next) {
+            ("0", Some('x')) => 16,
+            ("0", Some('o')) => 8,
+            ("0", Some('b')) => 2,
+            _ => 10,
         };
 
-        ($type:ty, { $( $name:ident : |$input:ident| $body:expr );*; }) => {
-            $( decl_parser!($type, $name : |$input| $body); )*
-        };
-    }
+        let mut float = false;
+        let mut valid = true;
 
-    decl_parser!(Token, {
-        Static : |input| {
-            let mut ret = Vec::new();
+        if radix == 10 {
+            self.scan_digits(10);
 
-            macro_rules! decl_token {
-                ($string:expr => $token:expr) => {
-                    if input.clone().take($string.len()).eq($string.chars()) {
-                        ret.push(($string.into(), $token))
-                    }
-                };
+            if self.fraction_follows() {
+                self.advance();
+                self.scan_digits(10);
+                float = true;
+            }
 
-                ($( $string:expr => $token:expr ),*,) => {
-                    $( decl_token!($string => $token); )*
-                }
+            if self.exponent_follows() {
+                self.advance();
+                self.advance_if(|ch| ch == '+' || ch == '-');
+                self.scan_digits(10);
+                float = true;
             }
+        } else {
+            self.advance();
+            valid = self.scan_digits(radix) > 0;
+        }
 
-            decl_token! {
-                // Braces
-                "(" => ParenLeft, ")" => ParenRight,
-                "{" => CurlyLeft, "}" => CurlyRight,
-                "[" => SquareLeft, "]" => SquareRight,
-
-                // Operators
-                "," => Comma,
-                "." => Dot,
-                "-" => Minus, "+" => Plus,
-                "/" => Slash, "*" => Star,
-                "=" => Equal, "==" => EqualEqual,
-                "!" => Bang, "!=" => BangEqual,
-                ">" => Greater, ">=" => GreaterEqual,
-                "<" => Less, "<=" => LessEqual,
-
-                // Keywords
-                "fn" => Function, "return" => Return,
-                "let" => Let,
-                "if" => If, "else" => Else,
-                "for" => For, "while" => While,
-
-                // Literals
-                "true" => Bool(true), "false" => Bool(false),
-                "nil" => Nil,
-
-                ";" => Semicolon,
-            };
+        // A number running directly into another word or fraction is malformed as a whole
+        if !valid
+            || matches!(self.peek(), Some(ch) if is_identifier_continue(ch))
+            || self.fraction_follows()
+        {
+            while self
+                .advance_if(|ch| ch == '.' || is_identifier_continue(ch))
+                .is_some()
+            {}
+
+            return Err(Error::MalformedNumber {
+                literal: self.lexeme.clone(),
+                span: self.lexeme_span(),
+            });
+        }
 
-            ret
-        };
+        let digits = self.lexeme.replace('_', "");
+        if float {
+            Ok(Token::Float(
+                digits.parse().expect("float literal is valid"),
+            ))
+        } else {
+            let digits = if radix == 10 { &digits } else { &digits[2..] };
+
+            // The digits are all valid, so this can only fail if the integer overflows
+            isize::from_str_radix(digits, radix)
+                .map(Token::Integer)
+                .map_err(|_| Error::IntegerOverflow {
+                    literal: self.lexeme.clone(),
+                    span: self.lexeme_span(),
+                })
+        }
+    }
 
-        Identifier : |input| {
-            let ident: String = input.take_while(|ch| *ch == '_' || ch.is_alphanumeric()).collect();
+    /// Consume digits of the given radix and `_` separators, returning the number of digits
+    fn scan_digits(&mut self, radix: u32) -> usize {
+        let mut count = 0;
 
-            match ident.chars().next() {
-                Some(ch) if !ch.is_numeric() => vec![(ident.clone(), Token::Identifier(ident))],
-                _ => vec![],
+        while let Some(ch) = self.advance_if(|ch| ch == '_' || ch.is_digit(radix)) {
+            if ch != '_' {
+                count += 1;
             }
+        }
+
+        count
+    }
+
+    /// Whether a fractional part (a dot followed by a digit) follows
+    fn fraction_follows(&mut self) -> bool {
+        self.peek() == Some('.') && matches!(self.peek_nth(1), Some(ch) if ch.is_ascii_digit())
+    }
+
+    /// Whether an exponent (`e`, an optional sign and a digit) follows
+    fn exponent_follows(&mut self) -> bool {
+        let digit_at = match (self.peek(), self.peek_nth(1)) {
+            (Some('e' | 'E'), Some('+' | '-')) => 2,
+            (Some('e' | 'E'), _) => 1,
+            _ => return false,
         };
 
-        Str : |input| {
-            let mut input = input.peekable();
+        matches!(self.peek_nth(digit_at), Some(ch) if ch.is_ascii_digit())
+    }
+
+    /// Scan the remainder of a string literal after its opening quote
+    fn scan_string(&mut self) -> Result<Token> {
+        let mut literal = String::new();
+        let mut invalid = None;
 
-            if input.next() == Some('"') {
-                let literal: String = input.take_while(|ch| *ch != '"').collect();
+        loop {
+            let escape_start = self.offset;
 
-                vec![(
-                    format!("\"{}\"", literal),
-                    Token::Str(literal),
-                )]
-            } else {
-                vec![]
+            match self.advance() {
+                None => {
+                    return Err(Error::UnterminatedString {
+                        span: self.lexeme_span(),
+                    })
+                }
+                Some('"') => break,
+                Some('\\') => {
+                    let start = self.lexeme.len() - 1;
+
+                    match self.scan_escape() {
+                        Some(ch) => literal.push(ch),
+                        None => {
+                            // Keep scanning so the rest of the string is consumed, but only report
+                            // the first invalid escape
+                            if invalid.is_none() {
+                                invalid = Some(Error::InvalidEscape {
+                                    escape: self.lexeme[start..].to_string(),
+                                    span: Span::new(self.file, escape_start, self.offset),
+                                });
+                            }
+                        }
+                    }
+                }
+                Some(ch) => literal.push(ch),
             }
-        };
+        }
 
-        Float : |input| {
-            let src: String = input.take_while(|ch| !ch.is_whitespace()).collect();
+        match invalid {
+            Some(err) => Err(err),
+            None => Ok(Token::Str(literal)),
+        }
+    }
 
-            if src.starts_with('-') { return vec![] }
+    /// Scan the remainder of an escape sequence after its backslash
+    fn scan_escape(&mut self) -> Option<char> {
+        match self.advance()? {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            'u' => {
+                self.advance_if(|ch| ch == '{')?;
+
+                let start = self.lexeme.len();
+                while self.advance_if(|ch| ch.is_ascii_hexdigit()).is_some() {}
+                let digits = &self.lexeme[start..];
+
+                let code = if (1..=6).contains(&digits.len()) {
+                    u32::from_str_radix(digits, 16).ok()
+                } else {
+                    None
+                };
 
-            src
-                .char_indices()
-                .map(|(i, _)| &src[..=i])
-                .filter_map(|sub| Some((sub.to_string(), Token::Float(sub.parse().ok()?))))
-                .collect()
-        };
+                self.advance_if(|ch| ch == '}')?;
+                char::from_u32(code?)
+            }
+            _ => None,
+        }
+    }
 
-        Integer : |input| {
-            let src: String = input.take_while(|ch| !ch.is_whitespace()).collect();
+    /// Scan the remainder of a raw string literal after its `r`
+    fn scan_raw_string(&mut self) -> Result<Token> {
+        let mut hashes = 0;
+        while self.advance_if(|ch| ch == '#').is_some() {
+            hashes += 1;
+        }
 
-            if src.starts_with('-') { return vec![] }
+        if self.advance_if(|ch| ch == '"').is_none() {
+            return Err(Error::InvalidRawStringDelimiter {
+                span: self.lexeme_span(),
+            });
+        }
 
-            src
-                .char_indices()
-                .map(|(i, _)| &src[..=i])
-                .filter_map(|sub| Some((sub.to_string(), Token::Integer(sub.parse().ok()?))))
-                .collect()
-        };
-    });
+        let start = self.lexeme.len();
+        loop {
+            match self.advance() {
+                None => {
+                    return Err(Error::UnterminatedString {
+                        span: self.lexeme_span(),
+                    })
+                }
+                Some('"') => {
+                    // Any `#`s which don't complete the closing delimiter are part of the literal
+                    let mut closing = 0;
+                    while closing < hashes && self.advance_if(|ch| ch == '#').is_some() {
+                        closing += 1;
+                    }
+
+                    if closing == hashes {
+                        let end = self.lexeme.len() - hashes - 1;
+                        return Ok(Token::Str(self.lexeme[start..end].to_string()));
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::lexer::token::Token::*;
+    use crate::source::SourceMap;
+
+    fn analyze(src: &str) -> Vec<Result<Token>> {
+        let file = SourceMap::new().add_repl_input(src.into());
+
+        Analyzer::new(src.chars(), file)
+            .map(|res| res.map(|tke| tke.token))
+            .collect()
+    }
 
     #[test]
     fn parses_tokens() {
@@ -168,49 +344,140 @@ mod tests {
             }
         }
 
-        let tokens: &[(&str, &[(&str, Token)])] = token_pairs![
+        let tokens: &[(&str, &[Token])] = token_pairs![
             // Braces
-            "(" => [("(", ParenLeft)], ")" => [(")", ParenRight)],
-            "{" => [("{", CurlyLeft)], "}" => [("}", CurlyRight)],
-            "[" => [("[", SquareLeft)], "]" => [("]", SquareRight)],
+            "(" => [ParenLeft], ")" => [ParenRight],
+            "{" => [CurlyLeft], "}" => [CurlyRight],
+            "[" => [SquareLeft], "]" => [SquareRight],
 
             // Operators
-            "," => [(",", Comma)],
-            "." => [(".", Dot)],
-            "-" => [("-", Minus)], "+" => [("+", Plus)],
-            "/" => [("/", Slash)], "*" => [("*", Star)],
-            "=" => [("=", Equal)], "==" => [("=", Equal), ("==", EqualEqual)],
-            "!" => [("!", Bang)], "!=" => [("!", Bang), ("!=", BangEqual)],
-            ">" => [(">", Greater)], ">=" => [(">", Greater), (">=", GreaterEqual)],
-            "<" => [("<", Less)], "<=" => [("<", Less), ("<=", LessEqual)],
-
-            // Keyword
-            "fn" => [("fn", Function), ("fn", Identifier("fn".into()))],
+            "," => [Comma],
+            "." => [Dot], ".." => [DotDot], "..." => [DotDot, Dot],
+            "-" => [Minus], "->" => [Arrow], "-->" => [Minus, Arrow], "+" => [Plus],
+            "/" => [Slash], "*" => [Star],
+            "|" => [Pipe], "||" => [Pipe, Pipe],
+            "=" => [Equal], "==" => [EqualEqual], "===" => [EqualEqual, Equal],
+            "!" => [Bang], "!=" => [BangEqual],
+            ">" => [Greater], ">=" => [GreaterEqual],
+            "<" => [Less], "<=" => [LessEqual],
+
+            // Keywords
+            "fn" => [Function], "fnord" => [Identifier("fnord".into())],
+            "let" => [Let], "return" => [Return],
+            "if" => [If], "else" => [Else],
+            "for" => [For], "while" => [While], "in" => [In],
+            "break" => [Break], "continue" => [Continue],
+            "interface" => [Interface], "instance" => [Instance],
 
             // Literals
-            "nil" => [("nil", Nil), ("nil", Identifier("nil".into()))],
-            "true" => [("true", Bool(true)), ("true", Identifier("true".into()))],
-            "false" => [("false", Bool(false)), ("false", Identifier("false".into()))],
-            "0" => [("0", Integer(0)), ("0", Float(0.0))],
-            "0.0" => [("0", Integer(0)), ("0", Float(0.0)), ("0.", Float(0.0)), ("0.0", Float(0.0))],
-            "\"string\"" => [("\"string\"", Str("string".into()))],
-
-            ";" => [(";", Semicolon)],
+            "nil" => [Nil],
+            "true" => [Bool(true)],
+            "false" => [Bool(false)],
+            "0" => [Integer(0)],
+            "0.0" => [Float(0.0)],
+            "0." => [Integer(0), Dot],
+            "0..10" => [Integer(0), DotDot, Integer(10)],
+            "12.5.x" => [Float(12.5), Dot, Identifier("x".into())],
+            "0xff" => [Integer(0xff)], "0o17" => [Integer(0o17)], "0b1010" => [Integer(0b1010)],
+            "0xDEAD_beef" => [Integer(0xdead_beef)],
+            "1_000_000" => [Integer(1_000_000)],
+            "1_000.000_1" => [Float(1_000.000_1)],
+            "1e-9" => [Float(1e-9)], "2.5E+3" => [Float(2.5e3)], "3e2" => [Float(300.0)],
+            "9223372036854775807" => [Integer(isize::MAX)],
+            "inf" => [Identifier("inf".into())], "nan" => [Identifier("nan".into())],
+            "NaN" => [Identifier("NaN".into())], "infinity" => [Identifier("infinity".into())],
+            "\"string\"" => [Str("string".into())],
+
+            // Identifiers
+            "r" => [Identifier("r".into())], "r2" => [Identifier("r2".into())],
+            "_under_score1" => [Identifier("_under_score1".into())],
+            "größe" => [Identifier("größe".into())],
+            "π" => [Identifier("π".into())],
+            "變數_2" => [Identifier("變數_2".into())],
+            "x\u{301}" => [Identifier("x\u{301}".into())],
+            "℘" => [Identifier("℘".into())],
+
+            // Labels
+            "'outer" => [Label("outer".into())],
+            "'_1" => [Label("_1".into())],
+
+            ";" => [Semicolon], ":" => [Colon], "?" => [Question], "??" => [Question, Question],
         ];
 
         for (src, correct) in tokens {
-            let mut parses = Token::parse_from(&mut src.chars());
-            parses.sort_by_key(|(src, _)| src.len());
+            let scanned = analyze(src);
 
             assert!(
-                parses.iter().cloned().eq(correct
-                    .iter()
-                    .cloned()
-                    .map(|(src, tk)| (src.to_string(), tk))),
-                "Parser results were incorrect\nParser Results: {:#?}\nCorrect Results: {:#?}",
-                parses,
+                scanned.iter().cloned().eq(correct.iter().cloned().map(Ok)),
+                "Scanned tokens were incorrect\nScanned: {:#?}\nCorrect: {:#?}",
+                scanned,
                 correct,
             );
         }
     }
+
+    #[test]
+    fn parses_strings() {
+        let strings: &[(&str, &str)] = &[
+            (r#""plain""#, "plain"),
+            (r#""tab\tnewline\n""#, "tab\tnewline\n"),
+            (
+                r#""quote \" and backslash \\""#,
+                "quote \" and backslash \\",
+            ),
+            (r#""\u{48}\u{1F600}\0""#, "H\u{1F600}\0"),
+            ("\"multi\nline\"", "multi\nline"),
+            (r#"r"raw \n""#, "raw \\n"),
+            (r###"r##"contains "# and ""##"###, "contains \"# and \""),
+            (r####"r###"ends with ##"##"###"####, "ends with ##\"##"),
+        ];
+
+        for (src, literal) in strings {
+            assert_eq!(
+                analyze(src),
+                [Ok(Str(literal.to_string()))],
+                "String {} was parsed incorrectly",
+                src,
+            );
+        }
+    }
+
+    #[test]
+    fn recognizes_malformed_tokens() {
+        let malformed: &[(&str, &str)] = &[
+            ("\"unterminated", "unterminated string literal"),
+            ("123abc", "malformed number literal '123abc'"),
+            ("1.2.3", "malformed number literal '1.2.3'"),
+            ("0x", "malformed number literal '0x'"),
+            ("0b102", "malformed number literal '0b102'"),
+            ("0xfg", "malformed number literal '0xfg'"),
+            ("1e", "malformed number literal '1e'"),
+            (
+                "9223372036854775808",
+                "integer literal '9223372036854775808' overflows",
+            ),
+            (
+                "0x1_0000_0000_0000_0000",
+                "integer literal '0x1_0000_0000_0000_0000' overflows",
+            ),
+            (
+                r#""a\qb""#,
+                r"invalid escape sequence '\q' in string literal",
+            ),
+            (
+                r#""\u{110000}""#,
+                r"invalid escape sequence '\u{110000}' in string literal",
+            ),
+            (r#"r#"open""#, "unterminated string literal"),
+            ("r##", "expected '\"' after raw string delimiter"),
+            ("'", "unrecognized character '\\''"),
+        ];
+
+        for (src, message) in malformed {
+            match analyze(src).as_slice() {
+                [Err(err)] => assert_eq!(&err.to_string(), message),
+                scanned => panic!("Scanning {:?} did not fail: {:#?}", src, scanned),
+            }
+        }
+    }
 }
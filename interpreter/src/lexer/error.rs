@@ -1,4 +1,54 @@
-#[derive(Debug, Clone, Copy)]
-pub enum Error {}
+use crate::source::Span;
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    UnrecognizedCharacter { character: char, span: Span },
+    UnterminatedString { span: Span },
+    UnterminatedComment { span: Span },
+    InvalidRawStringDelimiter { span: Span },
+    InvalidEscape { escape: String, span: Span },
+    MalformedNumber { literal: String, span: Span },
+    IntegerOverflow { literal: String, span: Span },
+}
+
+impl Error {
+    /// The span of source at which the error was encountered
+    pub fn span(&self) -> Span {
+        match self {
+            Self::UnrecognizedCharacter { span, .. }
+            | Self::UnterminatedString { span }
+            | Self::UnterminatedComment { span }
+            | Self::InvalidRawStringDelimiter { span }
+            | Self::InvalidEscape { span, .. }
+            | Self::MalformedNumber { span, .. }
+            | Self::IntegerOverflow { span, .. } => *span,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::UnrecognizedCharacter { character, .. } => {
+                write!(f, "unrecognized character {:?}", character)
+            }
+            Self::UnterminatedString { .. } => write!(f, "unterminated string literal"),
+            Self::UnterminatedComment { .. } => write!(f, "unterminated block comment"),
+            Self::InvalidRawStringDelimiter { .. } => {
+                write!(f, "expected '\"' after raw string delimiter")
+            }
+            Self::InvalidEscape { escape, .. } => {
+                write!(f, "invalid escape sequence '{}' in string literal", escape)
+            }
+            Self::MalformedNumber { literal, .. } => {
+                write!(f, "malformed number literal '{}'", literal)
+            }
+            Self::IntegerOverflow { literal, .. } => {
+                write!(f, "integer literal '{}' overflows", literal)
+            }
+        }
+    }
+}
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -2,106 +2,575 @@ pub mod analyze;
 pub mod error;
 pub mod token;
 
-use analyze::Parse;
+use crate::source::{FileId, Span};
 pub use error::{Error, Result};
-use std::iter::Peekable;
+use std::collections::VecDeque;
 pub use token::{
-    lexeme::{Lexeme, LexemeLocation},
+    lexeme::Lexeme,
+    trivia::{Trivia, TriviaKind},
     Token, TokenExt,
 };
 
+/// The maximum number of characters the [`Analyzer`] looks ahead of the current one
+pub const MAX_LOOKAHEAD: usize = 3;
+
 /// A lexical analysis
+///
+/// The source is read one character at a time with at most [`MAX_LOOKAHEAD`] characters of
+/// lookahead, so it may be streamed from any reader (see
+/// [`IntoCharIter`](crate::utils::char_iter::IntoCharIter)).
 #[derive(Debug, Clone)]
 pub struct Analyzer<S>
 where
     S: Iterator<Item = char>,
 {
     /// The source code to be analyzed
-    source: Peekable<S>,
+    source: S,
+
+    /// Characters read from the source which have not yet been consumed
+    lookahead: VecDeque<char>,
+
+    /// The raw content of the lexeme currently being analyzed
+    lexeme: String,
+
+    /// Byte offset of the start of the current lexeme
+    start: usize,
 
-    /// Position in the source (line, column)
-    position: (usize, usize),
+    /// Byte offset of the next character in the source
+    offset: usize,
 
-    /// Metadata on the [`Analysis`], including information about the source
-    meta: AnalysisMeta,
+    /// The file being analyzed
+    file: FileId,
+
+    /// Whether trivia is kept and attached to tokens
+    lossless: bool,
+
+    /// Trivia which has been analyzed but not yet attached to a token
+    trivia: Vec<Trivia>,
 }
 
 impl<S> Analyzer<S>
 where
-    S: Iterator<Item = char> + Clone,
+    S: Iterator<Item = char>,
 {
-    pub fn new(source: S, meta: AnalysisMeta) -> Self {
+    pub fn new(source: S, file: FileId) -> Self {
         Self {
-            source: source.peekable(),
-            position: (0, 0),
-            meta,
+            source,
+            lookahead: VecDeque::with_capacity(MAX_LOOKAHEAD),
+            lexeme: String::new(),
+            start: 0,
+            offset: 0,
+            file,
+            lossless: false,
+            trivia: Vec::new(),
         }
     }
-}
 
-impl<S> Iterator for Analyzer<S>
-where
-    S: Iterator<Item = char> + Clone,
-{
-    type Item = Result<TokenExt>;
+    /// Create an analysis which attaches whitespace and comments to the following tokens
+    ///
+    /// Together with the [`Analyzer::trailing_trivia`], the tokens' trivia and lexemes cover the
+    /// entire source. Lexical errors are analyzed as [`Token::Error`]s rather than returned, so
+    /// that their source is kept too.
+    pub fn lossless(source: S, file: FileId) -> Self {
+        Self {
+            lossless: true,
+            ..Self::new(source, file)
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        // Consume any leading whitespace before parsing
-        while self.source.peek()?.is_whitespace() {
-            if self.source.next() == Some('\n') {
-                self.position.0 += 1;
-                self.position.1 = 0;
+    /// Take the trivia following the last token, once the analysis is exhausted
+    pub fn trailing_trivia(&mut self) -> Vec<Trivia> {
+        std::mem::take(&mut self.trivia)
+    }
+
+    /// Peek at the character `n` characters ahead of the next one
+    fn peek_nth(&mut self, n: usize) -> Option<char> {
+        debug_assert!(n < MAX_LOOKAHEAD, "looked too far ahead");
+
+        while self.lookahead.len() <= n {
+            self.lookahead.push_back(self.source.next()?);
+        }
+
+        self.lookahead.get(n).copied()
+    }
+
+    /// Peek at the next character
+    fn peek(&mut self) -> Option<char> {
+        self.peek_nth(0)
+    }
+
+    /// Whether the remaining source starts with the given pattern
+    fn lookahead_is(&mut self, pattern: &str) -> bool {
+        pattern
+            .chars()
+            .enumerate()
+            .all(|(i, ch)| self.peek_nth(i) == Some(ch))
+    }
+
+    /// Consume a single character from the source, keeping track of the position and lexeme
+    fn advance(&mut self) -> Option<char> {
+        let ch = match self.lookahead.pop_front() {
+            Some(ch) => ch,
+            None => self.source.next()?,
+        };
+
+        self.offset += ch.len_utf8();
+        self.lexeme.push(ch);
+
+        Some(ch)
+    }
+
+    /// The span of the current lexeme
+    fn lexeme_span(&self) -> Span {
+        Span::new(self.file, self.start, self.offset)
+    }
+
+    /// Consume the next character if it satisfies the predicate
+    fn advance_if(&mut self, pred: impl FnOnce(char) -> bool) -> Option<char> {
+        match self.peek() {
+            Some(ch) if pred(ch) => self.advance(),
+            _ => None,
+        }
+    }
+
+    /// Consume any whitespace and comments preceding the next token
+    fn skip_trivia(&mut self) -> Result<()> {
+        loop {
+            self.start = self.offset;
+            self.lexeme.clear();
+
+            let kind = if self.lookahead_is("//") {
+                while !matches!(self.peek(), None | Some('\n')) {
+                    self.advance();
+                }
+                TriviaKind::LineComment
+            } else if self.lookahead_is("/*") {
+                self.skip_block_comment()?;
+                TriviaKind::BlockComment
+            } else if self.advance_if(char::is_whitespace).is_some() {
+                while self.advance_if(char::is_whitespace).is_some() {}
+                TriviaKind::Whitespace
             } else {
-                self.position.1 += 1;
+                return Ok(());
             };
+
+            if self.lossless {
+                let span = self.lexeme_span();
+                self.trivia.push(Trivia {
+                    kind,
+                    lexeme: Lexeme {
+                        content: std::mem::take(&mut self.lexeme),
+                        span,
+                    },
+                });
+            }
         }
+    }
 
-        let mut parsed = Token::parse_from(&mut self.source.clone());
-        parsed.reverse();
-        parsed.sort_by_key(|(src, _)| src.len());
+    /// Consume a (possibly nested) block comment
+    fn skip_block_comment(&mut self) -> Result<()> {
+        let mut depth = 0usize;
 
-        let (src, token) = parsed.pop()?;
+        loop {
+            if self.lookahead_is("/*") {
+                self.advance();
+                self.advance();
+                depth += 1;
+            } else if self.lookahead_is("*/") {
+                self.advance();
+                self.advance();
+                depth -= 1;
 
-        // Consume the token's characters from the source
-        self.source.advance_by(src.len()).unwrap();
+                if depth == 0 {
+                    return Ok(());
+                }
+            } else if self.advance().is_none() {
+                return Err(Error::UnterminatedComment {
+                    span: self.lexeme_span(),
+                });
+            }
+        }
+    }
+}
 
-        let position = self.position;
-        self.position.1 += src.len();
+impl<S> Iterator for Analyzer<S>
+where
+    S: Iterator<Item = char>,
+{
+    type Item = Result<TokenExt>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Consume any leading whitespace and comments before scanning
+        let token = match self.skip_trivia() {
+            Ok(()) => {
+                self.peek()?;
+                self.start = self.offset;
+                self.scan_token()
+            }
+            Err(err) => Err(err),
+        };
+        let token = match token {
+            Ok(token) => token,
+            Err(err) if self.lossless => Token::Error(err),
+            Err(err) => return Some(Err(err)),
+        };
 
         Some(Ok(TokenExt {
             token,
             lexeme: Lexeme {
-                content: src,
-                location: self.meta.lexeme_location(position),
+                span: self.lexeme_span(),
+                content: std::mem::take(&mut self.lexeme),
             },
+            trivia: std::mem::take(&mut self.trivia),
         }))
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct AnalysisMeta {
-    pub repl: bool,
-    pub file: Option<std::path::PathBuf>,
-}
+#[cfg(test)]
+mod benches {
+    extern crate test;
 
-impl Default for AnalysisMeta {
-    fn default() -> Self {
-        Self {
-            repl: false,
-            file: None,
+    use super::*;
+    use crate::source::SourceMap;
+    use test::Bencher;
+
+    /// A chunk of representative source, repeated to produce inputs of different sizes
+    const SAMPLE: &str = r##"
+        // Compute the nth fibonacci number
+        fn fib(n) {
+            /* recursion is the only way to loop, for now */
+            let a = fib(n - 1);
+            let b = fib(n - 2);
+            a + b * 1.0
         }
+
+        let greeting = "hello,\tworld\n";
+        let raw = r#"no \escapes"#;
+        fib(42) >= 267914296 != false
+    "##;
+
+    /// Analyze the sample repeated `repetitions` times, reporting throughput
+    ///
+    /// Analysis takes linear time, so throughput should not depend on the input size.
+    fn analyze_repeated(b: &mut Bencher, repetitions: usize) {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_repl_input(SAMPLE.repeat(repetitions));
+        let source = source_map.get(file).content().unwrap();
+
+        b.bytes = source.len() as u64;
+        b.iter(|| Analyzer::new(source.chars(), file).count());
+    }
+
+    #[bench]
+    fn analyze_small(b: &mut Bencher) {
+        analyze_repeated(b, 1);
+    }
+
+    #[bench]
+    fn analyze_medium(b: &mut Bencher) {
+        analyze_repeated(b, 64);
+    }
+
+    #[bench]
+    fn analyze_large(b: &mut Bencher) {
+        analyze_repeated(b, 4096);
     }
 }
 
-impl AnalysisMeta {
-    fn lexeme_location(&self, position: (usize, usize)) -> LexemeLocation {
-        if self.repl {
-            LexemeLocation::Repl
-        } else {
-            LexemeLocation::File {
-                path: self.file.clone(),
-                position,
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::SourceMap;
+
+    /// Analyze the source as a file in a fresh [`SourceMap`]
+    fn analyze(source: &str) -> (SourceMap, Vec<Result<TokenExt>>) {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_repl_input(source.into());
+        let results = Analyzer::new(source.chars(), file).collect();
+
+        (source_map, results)
+    }
+
+    fn tokens(source: &str) -> Vec<Token> {
+        analyze(source)
+            .1
+            .into_iter()
+            .map(|res| res.unwrap().token)
+            .collect()
+    }
+
+    /// The (line, column) positions at which each token or error starts
+    fn positions(source: &str) -> Vec<(usize, usize)> {
+        let (source_map, results) = analyze(source);
+
+        results
+            .iter()
+            .map(|res| match res {
+                Ok(tke) => tke.lexeme.span,
+                Err(err) => err.span(),
+            })
+            .map(|span| {
+                let location = source_map.location(span);
+                (location.line, location.column)
+            })
+            .collect()
+    }
+
+    /// The source covered by the span of each token or error
+    fn spanned(source: &str) -> Vec<&str> {
+        analyze(source)
+            .1
+            .iter()
+            .map(|res| match res {
+                Ok(tke) => tke.lexeme.span,
+                Err(err) => err.span(),
+            })
+            .map(|span| &source[span.range()])
+            .collect()
+    }
+
+    #[test]
+    fn skips_line_comments() {
+        let source = "let a = 1; // the answer / 42\na";
+
+        assert_eq!(
+            tokens(source),
+            [
+                Token::Let,
+                Token::Identifier("a".into()),
+                Token::Equal,
+                Token::Integer(1),
+                Token::Semicolon,
+                Token::Identifier("a".into()),
+            ]
+        );
+        assert_eq!(positions(source).last(), Some(&(1, 0)));
+    }
+
+    #[test]
+    fn skips_nested_block_comments() {
+        let source = "1 /* outer /* inner\n */ still outer */ / 2";
+
+        assert_eq!(
+            tokens(source),
+            [Token::Integer(1), Token::Slash, Token::Integer(2)]
+        );
+        assert_eq!(positions(source), [(0, 0), (1, 19), (1, 21)]);
+    }
+
+    #[test]
+    fn comment_at_end_of_source() {
+        assert!(tokens("// nothing here").is_empty());
+    }
+
+    #[test]
+    fn attaches_trivia_losslessly() {
+        let source = "a /* b */ // c\n  d ";
+        let file = SourceMap::new().add_repl_input(source.into());
+        let mut analyzer = Analyzer::lossless(source.chars(), file);
+
+        let tokens: Vec<_> = analyzer.by_ref().map(Result::unwrap).collect();
+        let trivia = |tke: &TokenExt| {
+            tke.trivia
+                .iter()
+                .map(|trivia| (trivia.kind, trivia.lexeme.content.clone()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(trivia(&tokens[0]), []);
+        assert_eq!(
+            trivia(&tokens[1]),
+            [
+                (TriviaKind::Whitespace, " ".into()),
+                (TriviaKind::BlockComment, "/* b */".into()),
+                (TriviaKind::Whitespace, " ".into()),
+                (TriviaKind::LineComment, "// c".into()),
+                (TriviaKind::Whitespace, "\n  ".into()),
+            ]
+        );
+        assert_eq!(&source[tokens[1].trivia[1].lexeme.span.range()], "/* b */");
+
+        let trailing = analyzer.trailing_trivia();
+        assert_eq!(trailing.len(), 1);
+        assert_eq!(trailing[0].lexeme.content, " ");
+    }
+
+    #[test]
+    fn discards_trivia_by_default() {
+        let (_, results) = analyze(" a /* b */ c ");
+
+        assert!(results
+            .iter()
+            .all(|res| res.as_ref().unwrap().trivia.is_empty()));
+    }
+
+    #[test]
+    fn spans_cover_lexemes() {
+        assert_eq!(
+            spanned("let  x=0x1f;\"a\\\"b\""),
+            ["let", "x", "=", "0x1f", ";", "\"a\\\"b\""]
+        );
+    }
+
+    #[test]
+    fn analyzes_streamed_source() {
+        use crate::source::FileName;
+        use crate::utils::char_iter::IntoCharIter;
+        use std::io;
+
+        let source = "let ünïcode = r#\"raw\"#; // comment\n1.5e3";
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_stream(FileName::Stdin);
+
+        let chars = source.as_bytes().chars().map(io::Result::unwrap);
+        let streamed: Vec<_> = Analyzer::new(source_map.record(file, chars), file)
+            .map(Result::unwrap)
+            .collect();
+        let (_, in_memory) = analyze(source);
+
+        assert!(streamed
+            .iter()
+            .map(|tke| &tke.token)
+            .eq(in_memory.iter().map(|res| &res.as_ref().unwrap().token)));
+        assert_eq!(
+            source_map.location(streamed[5].lexeme.span).to_string(),
+            "<stdin>:2:1"
+        );
+    }
+
+    #[test]
+    fn tracks_columns_in_characters() {
+        let source = "let größe = \"日本語\" + ü;\n  ñ";
+
+        assert_eq!(
+            tokens(source),
+            [
+                Token::Let,
+                Token::Identifier("größe".into()),
+                Token::Equal,
+                Token::Str("日本語".into()),
+                Token::Plus,
+                Token::Identifier("ü".into()),
+                Token::Semicolon,
+                Token::Identifier("ñ".into()),
+            ]
+        );
+        assert_eq!(
+            positions(source),
+            [
+                (0, 0),
+                (0, 4),
+                (0, 10),
+                (0, 12),
+                (0, 18),
+                (0, 20),
+                (0, 21),
+                (1, 2)
+            ]
+        );
+        assert_eq!(
+            spanned(source),
+            ["let", "größe", "=", "\"日本語\"", "+", "ü", ";", "ñ"]
+        );
+    }
+
+    #[test]
+    fn rejects_non_identifier_characters() {
+        let source = "a€b 🦀 ²";
+        let (_, results) = analyze(source);
+
+        assert!(matches!(
+            results.as_slice(),
+            [
+                Ok(_),
+                Err(Error::UnrecognizedCharacter {
+                    character: '€', ..
+                }),
+                Ok(_),
+                Err(Error::UnrecognizedCharacter {
+                    character: '🦀',
+                    ..
+                }),
+                Err(Error::UnrecognizedCharacter {
+                    character: '²', ..
+                }),
+            ]
+        ));
+        assert_eq!(positions(source), [(0, 0), (0, 1), (0, 2), (0, 4), (0, 6)]);
+    }
+
+    #[test]
+    fn reports_errors_and_continues() {
+        let source = "let @ = 12ab; # \"open";
+        let (_, results) = analyze(source);
+
+        assert!(matches!(
+            results.as_slice(),
+            [
+                Ok(TokenExt {
+                    token: Token::Let,
+                    ..
+                }),
+                Err(Error::UnrecognizedCharacter { character: '@', .. }),
+                Ok(TokenExt {
+                    token: Token::Equal,
+                    ..
+                }),
+                Err(Error::MalformedNumber { .. }),
+                Ok(TokenExt {
+                    token: Token::Semicolon,
+                    ..
+                }),
+                Err(Error::UnrecognizedCharacter { character: '#', .. }),
+                Err(Error::UnterminatedString { .. }),
+            ]
+        ));
+        assert_eq!(
+            spanned(source),
+            ["let", "@", "=", "12ab", ";", "#", "\"open"]
+        );
+    }
+
+    #[test]
+    fn tracks_lines_in_strings() {
+        assert_eq!(
+            positions("\"first\nsecond\" r\"\n\n\" end"),
+            [(0, 0), (1, 8), (3, 2)]
+        );
+    }
+
+    #[test]
+    fn reports_escape_location() {
+        let source = "x = \"line\n  \\x\"; y";
+        let (_, results) = analyze(source);
+
+        assert!(matches!(&results[2], Err(Error::InvalidEscape { escape, .. }) if escape == "\\x"));
+        assert_eq!(positions(source)[2], (1, 2));
+        assert_eq!(spanned(source)[2], "\\x");
+        assert!(matches!(
+            results[3],
+            Ok(TokenExt {
+                token: Token::Semicolon,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn reports_unterminated_comment() {
+        let source = "1 /* open /* */";
+        let (_, results) = analyze(source);
+
+        assert!(matches!(
+            results.as_slice(),
+            [
+                Ok(TokenExt {
+                    token: Token::Integer(1),
+                    ..
+                }),
+                Err(Error::UnterminatedComment { .. }),
+            ]
+        ));
+        assert_eq!(spanned(source), ["1", "/* open /* */"]);
     }
 }
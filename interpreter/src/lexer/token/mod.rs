@@ -1,6 +1,10 @@
 pub mod lexeme;
+pub mod trivia;
 
+use super::Error;
+use crate::{extract, extract_variant_method};
 use lexeme::Lexeme;
+use trivia::Trivia;
 
 /// A token with metadata
 #[derive(Debug, Clone, PartialEq)]
@@ -10,6 +14,9 @@ pub struct TokenExt {
 
     /// The lexeme from which the [`Token`] was parsed
     pub lexeme: Lexeme,
+
+    /// Whitespace and comments preceding the [`Token`], which are only kept by lossless analyses
+    pub trivia: Vec<Trivia>,
 }
 
 /// A raw token with no metadata
@@ -26,10 +33,12 @@ pub enum Token {
     // Operators
     Comma,
     Dot,
+    DotDot,
     Minus,
     Plus,
     Slash,
     Star,
+    Pipe,
     Bang,
     BangEqual,
     Equal,
@@ -38,6 +47,7 @@ pub enum Token {
     GreaterEqual,
     Less,
     LessEqual,
+    Arrow,
 
     // Keywords
     Function,
@@ -46,7 +56,12 @@ pub enum Token {
     If,
     Else,
     For,
+    In,
     While,
+    Break,
+    Continue,
+    Interface,
+    Instance,
 
     // Literals
     Nil,
@@ -57,5 +72,18 @@ pub enum Token {
 
     Identifier(String),
 
+    /// A loop label, without its leading `'`
+    Label(String),
+
     Semicolon,
+    Colon,
+    Question,
+
+    /// Source which could not be analyzed, which is only produced by lossless analyses
+    Error(Error),
+}
+
+impl Token {
+    extract_variant_method!(into_identifier(self) { Self::Identifier as (a): (String) });
+    extract_variant_method!(into_label(self) { Self::Label as (a): (String) });
 }
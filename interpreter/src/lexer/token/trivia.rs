@@ -0,0 +1,22 @@
+use super::lexeme::Lexeme;
+
+/// Source which is insignificant to the meaning of a program
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+
+    /// The lexeme from which the [`Trivia`] was parsed
+    pub lexeme: Lexeme,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// A run of whitespace
+    Whitespace,
+
+    /// A comment running to the end of the line, excluding the newline
+    LineComment,
+
+    /// A (possibly nested) block comment
+    BlockComment,
+}
@@ -1,94 +1,256 @@
 #![feature(result_cloned)]
-#![feature(maybe_uninit_extra)]
-#![feature(maybe_uninit_slice)]
-#![feature(maybe_uninit_uninit_array)]
 #![feature(box_into_inner)]
-#![feature(iter_advance_by)]
+#![cfg_attr(test, feature(test))]
 
 mod interpreter;
 mod lexer;
 mod parser;
+mod source;
+mod typeck;
 mod utils;
 
+use interpreter::Interpreter;
+use source::{FileName, SourceMap};
 use std::{
     env, fs,
-    io::{self, BufRead},
+    io::{self, BufRead, IsTerminal, Read},
+    process,
 };
+use typeck::Checker;
+use utils::char_iter::IntoCharIter;
 
-fn get_tokens(args: &[String]) -> io::Result<Vec<lexer::token::TokenExt>> {
-    use lexer::{AnalysisMeta, Analyzer};
+/// Report every lexical error among the analysis results, returning the tokens if there were none
+fn tokenize(
+    source_map: &SourceMap,
+    results: Vec<lexer::Result<lexer::token::TokenExt>>,
+) -> Option<Vec<lexer::token::TokenExt>> {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
 
-    if let Some(filename) = args.get(1) {
-        let file = fs::read_to_string(filename)?;
+    for res in results {
+        match res {
+            Ok(tke) => tokens.push(tke),
+            Err(err) => errors.push(err),
+        }
+    }
 
-        let meta = AnalysisMeta {
-            file: Some(filename.into()),
-            ..Default::default()
-        };
+    if report_lexer_errors(source_map, &errors) {
+        None
+    } else {
+        Some(tokens)
+    }
+}
+
+/// Report each lexical error, returning whether there were any
+fn report_lexer_errors(source_map: &SourceMap, errors: &[lexer::Error]) -> bool {
+    for err in errors {
+        eprintln!(
+            "Lexer error at {}: {}",
+            source_map.location(err.span()),
+            err
+        );
+    }
+
+    !errors.is_empty()
+}
 
-        Ok(Analyzer::new(file.chars(), meta)
-            .map(Result::unwrap)
-            .collect())
+/// Report every parser error of a parse, returning the syntax tree if there were none
+fn parse(
+    source_map: &SourceMap,
+    (program, errors): (parser::ast::Program, Vec<parser::Error>),
+) -> Option<parser::ast::Program> {
+    for err in &errors {
+        eprintln!(
+            "Parser error at {}: {}",
+            source_map.location(err.span()),
+            err
+        );
+    }
+
+    if errors.is_empty() {
+        Some(program)
     } else {
-        let mut line = String::new();
-        io::stdin().lock().read_line(&mut line)?;
+        None
+    }
+}
 
-        Ok(Analyzer::new(line.chars(), AnalysisMeta::default())
-            .map(Result::unwrap)
-            .collect())
+/// Report every type error, returning the type of the program if there were none
+fn typecheck(
+    source_map: &SourceMap,
+    checker: &mut Checker,
+    program: &parser::ast::Program,
+) -> Option<typeck::Scheme> {
+    match checker.check(program) {
+        Ok(ty) => Some(ty),
+        Err(errors) => {
+            for err in &errors {
+                eprintln!("Type error at {}: {}", source_map.location(err.span()), err);
+            }
+            None
+        }
     }
 }
 
-fn parse(tokens: &[lexer::token::TokenExt]) -> parser::Result<parser::ast::Ast> {
-    use parser::Parser;
+fn interpret(
+    interpreter: &mut Interpreter,
+    program: parser::ast::Program,
+) -> interpreter::Result<interpreter::ValueWrap> {
+    interpreter.run(&program)
+}
+
+/// Run a whole program, analyzing and parsing it as it is streamed from the reader
+///
+/// Only the syntax tree is kept in memory, not the source nor its tokens.
+fn run_program(source_map: &mut SourceMap, name: FileName, reader: impl Read) {
+    let file = source_map.add_stream(name);
+
+    let mut read_error = None;
+    let mut lexer_errors = Vec::new();
+    let chars = reader
+        .chars()
+        .map_while(|res| res.map_err(|err| read_error = Some(err)).ok());
+    let tokens = lexer::Analyzer::new(source_map.record(file, chars), file)
+        .filter_map(|res| res.map_err(|err| lexer_errors.push(err)).ok());
+    let parse_result = parser::Parser::new(tokens).parse();
 
-    Parser::new(tokens.iter().cloned()).parse()
+    if let Some(err) = read_error {
+        eprintln!("Error reading {}: {}", source_map.get(file).name(), err);
+        process::exit(1);
+    }
+    if report_lexer_errors(source_map, &lexer_errors) {
+        process::exit(1);
+    }
+    let parsed = match parse(source_map, parse_result) {
+        Some(ast) => ast,
+        None => process::exit(1),
+    };
+    eprintln!("Parsed: {:#?}", parsed);
+
+    let ty = match typecheck(source_map, &mut Checker::new(), &parsed) {
+        Some(ty) => ty,
+        None => process::exit(1),
+    };
+    eprintln!("Type: {}", ty);
+
+    let res = match interpret(&mut Interpreter::new(), parsed) {
+        Ok(res) => res,
+        Err(err) => {
+            eprintln!("Runtime error:\n{}", err);
+            process::exit(1);
+        }
+    };
+    eprintln!("Result: {:#?}", res);
 }
 
-fn interpret(ast: parser::ast::Ast) -> interpreter::Result<interpreter::ValueWrap> {
-    use interpreter::Interpreter;
+/// Print the outline of a file's concrete syntax tree, reporting its lexical and syntax errors
+fn print_cst(source_map: &mut SourceMap, path: &str) -> io::Result<()> {
+    let file = source_map.add_file(FileName::Path(path.into()), fs::read_to_string(path)?);
+    let content = source_map.get(file).content().expect("files are kept");
+    let (cst, errors) = parser::cst::Cst::parse(content.chars(), file);
+
+    print!("{}", cst.outline());
+    for err in cst.lexer_errors() {
+        eprintln!(
+            "Lexer error at {}: {}",
+            source_map.location(err.span()),
+            err
+        );
+    }
+    for err in &errors {
+        eprintln!(
+            "Parser error at {}: {}",
+            source_map.location(err.span()),
+            err
+        );
+    }
 
-    Interpreter::new().interpret(&ast)
+    Ok(())
 }
 
 fn main() -> io::Result<()> {
     let args: Vec<_> = env::args().collect();
 
-    if args.len() > 1 {
-        let tokens = get_tokens(&args)?;
-        eprintln!("Tokens: {:#?}", tokens);
+    let mut source_map = SourceMap::new();
+    let stdin = io::stdin();
 
-        let parsed = parse(&tokens).unwrap();
-        eprintln!("Parsed: {:#?}", parsed);
+    match args.get(1).map(String::as_str) {
+        Some("--cst") => match args.get(2) {
+            Some(path) => print_cst(&mut source_map, path)?,
+            None => {
+                eprintln!("Usage: {} --cst <file>", args[0]);
+                process::exit(2);
+            }
+        },
+        Some("-") => run_program(&mut source_map, FileName::Stdin, stdin.lock()),
+        Some(filename) => run_program(
+            &mut source_map,
+            FileName::Path(filename.into()),
+            io::BufReader::new(fs::File::open(filename)?),
+        ),
+        None if !stdin.is_terminal() => run_program(&mut source_map, FileName::Stdin, stdin.lock()),
+        None => {
+            // A single interpreter and checker are kept for the whole session so that
+            // declarations persist
+            let mut interpreter = Interpreter::new();
+            let mut checker = Checker::new();
 
-        let res = interpret(parsed).unwrap();
-        eprintln!("Result: {:#?}", res);
-    } else {
-        loop {
-            eprint!("> ");
-            let tokens = get_tokens(&args)?;
-            eprintln!(
-                "Tokens: {:#?}",
-                tokens.iter().map(|tke| &tke.token).collect::<Vec<_>>()
-            );
-
-            let parsed = match parse(&tokens) {
-                Ok(ast) => ast,
-                Err(err) => {
-                    eprintln!("Parser error:\n{:#?}", err);
-                    continue;
+            loop {
+                eprint!("> ");
+
+                let mut line = String::new();
+                if stdin.lock().read_line(&mut line)? == 0 {
+                    break;
                 }
-            };
-            eprintln!("Parsed: {:#?}", parsed);
 
-            let res = match interpret(parsed) {
-                Ok(ast) => ast,
-                Err(err) => {
-                    eprintln!("Runtime error:\n{}", err);
+                if let Some(command) = line.trim().strip_prefix(':') {
+                    match command {
+                        "reset" => {
+                            interpreter = Interpreter::new();
+                            checker = Checker::new();
+                            eprintln!("Reset interpreter state");
+                        }
+                        _ => eprintln!("Unknown REPL command ':{}'", command),
+                    }
                     continue;
                 }
-            };
-            eprintln!("Result: {:#?}", res);
+
+                let file = source_map.add_repl_input(line);
+                let content = source_map
+                    .get(file)
+                    .content()
+                    .expect("REPL inputs are kept");
+                let results = lexer::Analyzer::new(content.chars(), file).collect();
+                let tokens = match tokenize(&source_map, results) {
+                    Some(tokens) => tokens,
+                    None => continue,
+                };
+                eprintln!(
+                    "Tokens: {:#?}",
+                    tokens.iter().map(|tke| &tke.token).collect::<Vec<_>>()
+                );
+
+                let parse_result = parser::Parser::new(tokens.into_iter()).parse();
+                let parsed = match parse(&source_map, parse_result) {
+                    Some(ast) => ast,
+                    None => continue,
+                };
+                eprintln!("Parsed: {:#?}", parsed);
+
+                let ty = match typecheck(&source_map, &mut checker, &parsed) {
+                    Some(ty) => ty,
+                    None => continue,
+                };
+                eprintln!("Type: {}", ty);
+
+                let res = match interpret(&mut interpreter, parsed) {
+                    Ok(ast) => ast,
+                    Err(err) => {
+                        eprintln!("Runtime error:\n{}", err);
+                        continue;
+                    }
+                };
+                eprintln!("Result: {:#?}", res);
+            }
         }
     }
 
@@ -1,4 +1,4 @@
-use crate::lexer::Token;
+use crate::lexer::{Token, TokenExt};
 use crate::parser::{Error, Result};
 use std::convert::TryFrom;
 
@@ -12,17 +12,20 @@ pub enum Literal {
     Str(String),
 }
 
-impl TryFrom<Token> for Literal {
+impl TryFrom<TokenExt> for Literal {
     type Error = Error;
-    fn try_from(token: Token) -> Result<Self> {
-        match token {
+    fn try_from(tke: TokenExt) -> Result<Self> {
+        match tke.token {
             Token::Nil => Ok(Self::Nil),
             Token::Bool(val) => Ok(Self::Bool(val)),
             Token::Integer(val) => Ok(Self::Integer(val)),
             Token::Float(val) => Ok(Self::Float(val)),
             Token::Str(val) => Ok(Self::Str(val)),
 
-            _ => Err(Error::Internal("token was not a literal")),
+            _ => Err(Error::Internal {
+                message: "token was not a literal",
+                span: tke.lexeme.span,
+            }),
         }
     }
 }
@@ -1,49 +1,194 @@
 pub mod literal;
 pub mod operation;
+pub mod type_expr;
 
+use crate::source::Span;
 use crate::{extract, extract_variant_method};
 pub use literal::Literal;
 pub use operation::{Operation, Operator};
+pub use type_expr::{TypeExpr, TypeParameter};
+
+/// A whole Smoke program, being a sequence of declarations and expressions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program(pub Vec<Ast>);
 
 /// A Smoke Abstract Syntax Tree
+///
+/// Each syntax tree keeps the span of its principal token, such as its keyword, operator or name,
+/// by which it is located in errors.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Ast {
     /// A literal value
-    Literal(Literal),
+    Literal(Literal, Span),
+
+    /// A variable declaration, with an optional type annotation
+    Declaration {
+        name: String,
+        annotation: Option<TypeExpr>,
+        value: Box<Self>,
+        span: Span,
+    },
 
-    /// A variable declaration
-    Declaration { name: String, value: Box<Self> },
+    /// An update of the nearest binding of a name
+    Assignment {
+        name: String,
+        value: Box<Self>,
+        span: Span,
+    },
 
     /// A reference to a defined identifier
-    Reference(String),
+    Reference(String, Span),
 
-    /// A grouping of one or more syntax trees
-    Grouping(Vec<Self>),
+    /// A grouping of statements, valued by its trailing expression or otherwise unit
+    Grouping(Vec<Self>, Option<Box<Self>>, Span),
 
     /// The result of an operation
-    Operation(Operation),
+    Operation(Operation, Span),
+
+    /// A choice between two syntax trees, the alternative being unit if omitted
+    ///
+    /// With a binding, as in `if let x = option`, the condition is an option whose value is bound
+    /// in the consequence if it isn't nil.
+    Conditional {
+        binding: Option<String>,
+        condition: Box<Self>,
+        consequence: Box<Self>,
+        alternative: Option<Box<Self>>,
+        span: Span,
+    },
+
+    /// A loop repeating its body while the condition is true
+    ///
+    /// A loop is valued if a break gives it a value, in which case it is an option of that value
+    /// and otherwise unit.
+    While {
+        label: Option<String>,
+        valued: bool,
+        condition: Box<Self>,
+        body: Box<Self>,
+        span: Span,
+    },
+
+    /// A loop running its body with each item of an iterable bound in turn
+    For {
+        label: Option<String>,
+        valued: bool,
+        binding: String,
+        iterable: Box<Self>,
+        body: Box<Self>,
+        span: Span,
+    },
+
+    /// An exit from the labelled or innermost loop, giving it a value or otherwise nil if the loop
+    /// is valued
+    Break {
+        label: Option<String>,
+        value: Option<Box<Self>>,
+        span: Span,
+    },
+
+    /// A skip to the next iteration of the labelled or innermost loop
+    Continue { label: Option<String>, span: Span },
 
-    /// A function taking arguments and returning a value
+    /// An exit from the innermost function, returning the value or otherwise unit
+    Return {
+        value: Option<Box<Self>>,
+        span: Span,
+    },
+
+    /// A function taking arguments and returning a value, with an optional output annotation
+    ///
+    /// Its type parameters may be used in the annotations of the function and of its body.
     Function {
-        arguments: Vec<String>,
+        type_parameters: Vec<TypeParameter>,
+        arguments: Vec<Parameter>,
+        output: Option<TypeExpr>,
         body: Box<Self>,
+        span: Span,
     },
 
     /// The application of a function to one or more values
     FunctionApplication {
         function: Box<Self>,
         arguments: Vec<Self>,
+        span: Span,
+    },
+
+    /// An interface declaration, with the signatures of the methods its instances implement
+    Interface {
+        name: String,
+        methods: Vec<Signature>,
+        span: Span,
     },
+
+    /// An instance of an interface for a type, located at the interface's name
+    ///
+    /// Each method is a [`Ast::Declaration`] of an [`Ast::Function`], as parsed from a named
+    /// function.
+    Instance {
+        interface: String,
+        ty: TypeExpr,
+        methods: Vec<Self>,
+        span: Span,
+    },
+
+    /// A syntax tree which could not be parsed
+    ///
+    /// This is only produced together with a parser error, so it is never interpreted.
+    Error,
+}
+
+/// An argument binding of a function, with an optional type annotation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    pub annotation: Option<TypeExpr>,
+}
+
+/// The signature of a method of an interface, in which `Self` is the type implementing it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+    pub output: Option<TypeExpr>,
+    pub span: Span,
 }
 
 impl Ast {
-    extract_variant_method!(as_literal(&self) { Self::Literal as (a): (&Literal) });
-    extract_variant_method!(as_reference(&self) { Self::Reference as (a): (&str) });
-    extract_variant_method!(as_operation(&self) { Self::Operation as (a): (&Operation) });
-    extract_variant_method!(as_grouping(&self) { Self::Grouping as (a): (&[Self]) });
-
-    extract_variant_method!(into_literal(self) { Self::Literal as (a): (Literal) });
-    extract_variant_method!(into_reference(self) { Self::Reference as (a): (String) });
-    extract_variant_method!(into_operation(self) { Self::Operation as (a): (Operation) });
-    extract_variant_method!(into_grouping(self) { Self::Grouping as (a): (Vec<Self>) });
+    /// The span of the principal token of the syntax tree
+    ///
+    /// # Panics
+    ///
+    /// Panics on [`Ast::Error`], which isn't located.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Literal(_, span)
+            | Self::Reference(_, span)
+            | Self::Grouping(_, _, span)
+            | Self::Operation(_, span)
+            | Self::Declaration { span, .. }
+            | Self::Assignment { span, .. }
+            | Self::Conditional { span, .. }
+            | Self::While { span, .. }
+            | Self::For { span, .. }
+            | Self::Break { span, .. }
+            | Self::Continue { span, .. }
+            | Self::Return { span, .. }
+            | Self::Function { span, .. }
+            | Self::FunctionApplication { span, .. }
+            | Self::Interface { span, .. }
+            | Self::Instance { span, .. } => *span,
+            Self::Error => panic!("erroneous syntax trees have no span"),
+        }
+    }
+
+    extract_variant_method!(as_literal(&self) { Self::Literal as (a, b): (&Literal, &Span) });
+    extract_variant_method!(as_reference(&self) { Self::Reference as (a, b): (&String, &Span) });
+    extract_variant_method!(as_operation(&self) { Self::Operation as (a, b): (&Operation, &Span) });
+    extract_variant_method!(as_grouping(&self) { Self::Grouping as (a, b, c): (&Vec<Self>, &Option<Box<Self>>, &Span) });
+
+    extract_variant_method!(into_literal(self) { Self::Literal as (a, b): (Literal, Span) });
+    extract_variant_method!(into_reference(self) { Self::Reference as (a, b): (String, Span) });
+    extract_variant_method!(into_operation(self) { Self::Operation as (a, b): (Operation, Span) });
+    extract_variant_method!(into_grouping(self) { Self::Grouping as (a, b, c): (Vec<Self>, Option<Box<Self>>, Span) });
 }
@@ -1,5 +1,5 @@
 use super::Ast;
-use crate::lexer::Token;
+use crate::lexer::{Token, TokenExt};
 use crate::parser::{Error, Result};
 use std::convert::TryFrom;
 
@@ -50,12 +50,13 @@ pub enum Operator {
     Subtract,
     Multiply,
     Divide,
+    Range,
 }
 
-impl TryFrom<&Token> for Operator {
+impl TryFrom<&TokenExt> for Operator {
     type Error = crate::parser::Error;
-    fn try_from(token: &Token) -> Result<Self> {
-        match token {
+    fn try_from(tke: &TokenExt) -> Result<Self> {
+        match tke.token {
             Token::Bang => Ok(Self::Not),
 
             Token::EqualEqual => Ok(Self::Equal),
@@ -67,27 +68,51 @@ impl TryFrom<&Token> for Operator {
             Token::Plus => Ok(Self::Add),
             Token::Star => Ok(Self::Multiply),
             Token::Slash => Ok(Self::Divide),
+            Token::DotDot => Ok(Self::Range),
 
             // Ambiguous tokens
-            Token::Minus => Err(Error::Internal("token was ambiguous")),
+            Token::Minus => Err(Error::Internal {
+                message: "token was ambiguous",
+                span: tke.lexeme.span,
+            }),
 
-            _ => Err(Error::Internal("token did not correspond to any operator")),
+            _ => Err(Error::Internal {
+                message: "token did not correspond to any operator",
+                span: tke.lexeme.span,
+            }),
         }
     }
 }
 
 impl Operator {
-    pub fn try_from_token_unary(token: &Token) -> Result<Self> {
-        match token {
+    pub fn try_from_token_unary(tke: &TokenExt) -> Result<Self> {
+        match tke.token {
             Token::Minus => Ok(Self::Negate),
-            _ => TryFrom::try_from(token),
+            _ => TryFrom::try_from(tke),
         }
     }
 
-    pub fn try_from_token_binary(token: &Token) -> Result<Self> {
-        match token {
+    pub fn try_from_token_binary(tke: &TokenExt) -> Result<Self> {
+        match tke.token {
             Token::Minus => Ok(Self::Subtract),
-            _ => TryFrom::try_from(token),
+            _ => TryFrom::try_from(tke),
+        }
+    }
+
+    /// The method of a builtin interface which the operator applies, if it is overloaded
+    ///
+    /// The comparisons other than `<` apply `lt` with their operands swapped or its result negated,
+    /// and `!=` negates the result of `eq`.
+    pub fn method(self) -> Option<&'static str> {
+        match self {
+            Self::Equal | Self::NotEqual => Some("eq"),
+            Self::Greater | Self::GreaterEqual | Self::Less | Self::LessEqual => Some("lt"),
+            Self::Add => Some("add"),
+            Self::Subtract => Some("sub"),
+            Self::Multiply => Some("mul"),
+            Self::Divide => Some("div"),
+            Self::Negate => Some("neg"),
+            Self::Not | Self::Range => None,
         }
     }
 }
@@ -0,0 +1,38 @@
+use crate::source::Span;
+
+/// A type parameter of a function, with the names and spans of the interfaces bounding it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeParameter {
+    pub name: String,
+    pub bounds: Vec<(String, Span)>,
+}
+
+/// A type as written in an annotation
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeExpr {
+    /// A type referred to by name and applied to any type arguments, such as `Integer`
+    ///
+    /// An optional type `T?` is parsed as `Option<T>`, spanning the `?`.
+    Named {
+        name: String,
+        arguments: Vec<TypeExpr>,
+        span: Span,
+    },
+
+    /// The type of functions, such as `fn(Integer, Integer) -> Bool`, returning unit if the
+    /// output is omitted
+    Function {
+        parameters: Vec<TypeExpr>,
+        output: Option<Box<TypeExpr>>,
+        span: Span,
+    },
+}
+
+impl TypeExpr {
+    /// The span of source naming the type, or of the `fn` keyword of a function type
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Named { span, .. } | Self::Function { span, .. } => *span,
+        }
+    }
+}
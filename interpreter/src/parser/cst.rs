@@ -0,0 +1,340 @@
+use super::{Error, Parser};
+use crate::lexer::{self, Analyzer, Token, TokenExt, Trivia};
+use crate::source::FileId;
+use std::fmt::{self, Display, Formatter};
+
+/// A lossless Concrete Syntax Tree, with a node for each construct of the grammar
+///
+/// Built from a lossless analysis, a [`Cst`] prints back to exactly the analyzed source, even if
+/// it has lexical or syntax errors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cst {
+    pub root: Node,
+
+    /// Whitespace and comments following the last token
+    pub trailing_trivia: Vec<Trivia>,
+}
+
+impl Cst {
+    /// Analyze and parse source into a tree, returning it together with every syntax error
+    ///
+    /// Lexical errors are kept in the tree as [`Token::Error`]s, and found by
+    /// [`Cst::lexer_errors`].
+    pub fn parse<S>(source: S, file: FileId) -> (Self, Vec<Error>)
+    where
+        S: Iterator<Item = char>,
+    {
+        let mut analyzer = Analyzer::lossless(source, file);
+        let mut parser = Parser::recording(analyzer.by_ref().map_while(Result::ok));
+        let (_, errors) = parser.parse();
+        let events = parser.events.take().expect("the parser was recording");
+
+        (Self::build(events, analyzer.trailing_trivia()), errors)
+    }
+
+    /// Build a tree from the events of a parse and the trailing trivia of its analysis
+    fn build(events: Vec<Event>, trailing_trivia: Vec<Trivia>) -> Self {
+        let mut stack = vec![Node::new(NodeKind::Root)];
+
+        for event in events {
+            match event {
+                Event::Start(kind) => stack.push(Node::new(kind)),
+                Event::Token(tke) => stack
+                    .last_mut()
+                    .expect("root is on the stack")
+                    .children
+                    .push(Element::Token(tke)),
+                Event::Finish => {
+                    let node = stack.pop().expect("node is on the stack");
+                    stack
+                        .last_mut()
+                        .expect("root is on the stack")
+                        .children
+                        .push(Element::Node(node));
+                }
+            }
+        }
+
+        assert_eq!(stack.len(), 1, "every node is finished");
+        Self {
+            root: stack.pop().expect("root is on the stack"),
+            trailing_trivia,
+        }
+    }
+
+    /// An indented outline of the tree's nodes and tokens, one per line
+    pub fn outline(&self) -> String {
+        let mut outline = String::new();
+        self.root.outline(0, &mut outline);
+
+        outline
+    }
+
+    /// Every lexical error kept in the tree, in source order
+    pub fn lexer_errors(&self) -> Vec<&lexer::Error> {
+        let mut errors = Vec::new();
+        self.root.lexer_errors(&mut errors);
+
+        errors
+    }
+}
+
+impl Display for Cst {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.root)?;
+
+        self.trailing_trivia
+            .iter()
+            .try_for_each(|trivia| write!(f, "{}", trivia.lexeme.content))
+    }
+}
+
+/// A step of a parse, from which a [`Cst`] is built
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// The start of a node, which contains everything up to the matching [`Event::Finish`]
+    Start(NodeKind),
+    Token(TokenExt),
+    Finish,
+}
+
+/// A node in a [`Cst`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub children: Vec<Element>,
+}
+
+impl Node {
+    fn new(kind: NodeKind) -> Self {
+        Self {
+            kind,
+            children: Vec::new(),
+        }
+    }
+
+    fn outline(&self, depth: usize, outline: &mut String) {
+        outline.push_str(&format!(
+            "{:indent$}{:?}\n",
+            "",
+            self.kind,
+            indent = depth * 2
+        ));
+
+        for child in &self.children {
+            match child {
+                Element::Node(node) => node.outline(depth + 1, outline),
+                Element::Token(tke) => outline.push_str(&format!(
+                    "{:indent$}{:?}\n",
+                    "",
+                    tke.lexeme.content,
+                    indent = (depth + 1) * 2
+                )),
+            }
+        }
+    }
+
+    fn lexer_errors<'a>(&'a self, errors: &mut Vec<&'a lexer::Error>) {
+        for child in &self.children {
+            match child {
+                Element::Node(node) => node.lexer_errors(errors),
+                Element::Token(TokenExt {
+                    token: Token::Error(err),
+                    ..
+                }) => errors.push(err),
+                Element::Token(_) => {}
+            }
+        }
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.children.iter().try_for_each(|child| match child {
+            Element::Node(node) => write!(f, "{}", node),
+            Element::Token(tke) => {
+                for trivia in &tke.trivia {
+                    write!(f, "{}", trivia.lexeme.content)?;
+                }
+                write!(f, "{}", tke.lexeme.content)
+            }
+        })
+    }
+}
+
+/// The construct of the grammar which a [`Node`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    /// The whole source
+    Root,
+
+    /// A `let` declaration, or a named function
+    Declaration,
+    Assignment,
+    Reference,
+    Literal,
+
+    /// A parenthesized expression
+    Paren,
+
+    /// A `{}`-delimited block of statements
+    Block,
+
+    Unary,
+    Binary,
+    Conditional,
+    While,
+    For,
+    Break,
+    Continue,
+    Return,
+
+    /// An anonymous function
+    Function,
+    Application,
+
+    /// A parameter of a function, with its annotation if any
+    Parameter,
+    TypeParameter,
+
+    /// A type annotation, or part of one
+    Type,
+
+    Interface,
+
+    /// The signature of a method in an interface
+    Signature,
+    Instance,
+
+    /// Tokens which could not be parsed, including lexical errors
+    Error,
+}
+
+/// A child of a [`Node`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Element {
+    Node(Node),
+    Token(TokenExt),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::SourceMap;
+
+    fn parse(source: &str) -> (Cst, Vec<Error>) {
+        let file = SourceMap::new().add_repl_input(source.into());
+        Cst::parse(source.chars(), file)
+    }
+
+    /// The kinds of the nodes directly under the root
+    fn statements(cst: &Cst) -> Vec<NodeKind> {
+        cst.root
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                Element::Node(node) => Some(node.kind),
+                Element::Token(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn prints_original_source() {
+        let sources = [
+            "",
+            "   \n\t ",
+            "// only a comment",
+            "let x = 1;",
+            "  fn add(a, b) /* sum */ {\n    a + b // the result\n}\n\n",
+            "{ /* nested /* comments */ */ f(1, 2) }   ",
+            "r#\"raw\"# \"esc\\\"aped\" 0x_ff 1e-3",
+            "unbalanced ) ] } ( [ { (",
+            "let @ = 12ab; # \"open",
+            "1 + /* open",
+            "let x: Integer? = if let y = z { y } else { nil }",
+            "interface I { fn m(a: Self) -> Bool }\ninstance I for Unit { fn m(a) true }",
+        ];
+
+        for source in &sources {
+            assert_eq!(&parse(source).0.to_string(), source);
+        }
+    }
+
+    #[test]
+    fn builds_syntax_nodes() {
+        let (cst, errors) = parse("let x = -f(1) + 2; fn g<T>(a: T) { return a }; x = 3");
+        assert_eq!(errors, []);
+        assert_eq!(
+            statements(&cst),
+            [
+                NodeKind::Declaration,
+                NodeKind::Declaration,
+                NodeKind::Assignment
+            ]
+        );
+
+        assert_eq!(
+            parse("-f(1) + 2").0.outline(),
+            "Root
+  Binary
+    Unary
+      \"-\"
+      Application
+        Reference
+          \"f\"
+        \"(\"
+        Literal
+          \"1\"
+        \")\"
+    \"+\"
+    Literal
+      \"2\"
+"
+        );
+    }
+
+    #[test]
+    fn keeps_errors_in_the_tree() {
+        let (cst, errors) = parse("let a = @; 1 2; }");
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert_eq!(
+            statements(&cst),
+            [
+                NodeKind::Declaration,
+                NodeKind::Literal,
+                NodeKind::Error,
+                NodeKind::Error
+            ]
+        );
+
+        match &cst.root.children[0] {
+            Element::Node(declaration) => assert!(matches!(
+                declaration.children.last(),
+                Some(Element::Node(Node {
+                    kind: NodeKind::Error,
+                    ..
+                }))
+            )),
+            Element::Token(_) => unreachable!(),
+        }
+
+        // A lexical error may be the target of an assignment
+        let (cst, errors) = parse("@ = 1");
+        assert!(
+            matches!(&errors[..], [Error::InvalidAssignmentTarget { .. }]),
+            "{:?}",
+            errors
+        );
+        assert_eq!(statements(&cst), [NodeKind::Assignment]);
+
+        let (cst, _) = parse("let a = @ + \"b; /* c");
+        assert!(matches!(
+            &cst.lexer_errors()[..],
+            [
+                lexer::Error::UnrecognizedCharacter { character: '@', .. },
+                lexer::Error::UnterminatedString { .. },
+            ]
+        ));
+    }
+}
@@ -1,8 +1,78 @@
-#[derive(Debug, Clone)]
+use crate::source::Span;
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Error {
-    Internal(&'static str),
+    Internal {
+        message: &'static str,
+        span: Span,
+    },
+
+    /// The span of an unexpected end of source is that of the last token
+    UnexpectedToken {
+        expected: String,
+        found: String,
+        span: Span,
+    },
+
+    /// A `break` or `continue` outside of any loop
+    OutsideLoop {
+        keyword: String,
+        span: Span,
+    },
+
+    UndefinedLabel {
+        label: String,
+        span: Span,
+    },
+
+    OutsideFunction {
+        span: Span,
+    },
+
+    /// An assignment to something other than a name, located at the `=`
+    InvalidAssignmentTarget {
+        span: Span,
+    },
+
+    /// An interface or instance declaration which isn't at the top level of the program
+    NestedDeclaration {
+        keyword: String,
+        span: Span,
+    },
+}
+
+impl Error {
+    /// The span of source at which the error was encountered
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Internal { span, .. }
+            | Self::UnexpectedToken { span, .. }
+            | Self::OutsideLoop { span, .. }
+            | Self::UndefinedLabel { span, .. }
+            | Self::OutsideFunction { span }
+            | Self::InvalidAssignmentTarget { span }
+            | Self::NestedDeclaration { span, .. } => *span,
+        }
+    }
+}
 
-    UnexpectedToken { expected: String, found: String },
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Internal { message, .. } => write!(f, "internal parser error: {}", message),
+            Self::UnexpectedToken {
+                expected, found, ..
+            } => write!(f, "expected {} but found {}", expected, found),
+            Self::OutsideLoop { keyword, .. } => write!(f, "'{}' outside of a loop", keyword),
+            Self::UndefinedLabel { label, .. } => write!(f, "undefined loop label {}", label),
+            Self::OutsideFunction { .. } => write!(f, "'return' outside of a function"),
+            Self::InvalidAssignmentTarget { .. } => write!(f, "only names can be assigned to"),
+            Self::NestedDeclaration { keyword, .. } => {
+                write!(f, "'{}' declarations must be at the top level", keyword)
+            }
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,8 +1,11 @@
 pub mod ast;
+pub mod cst;
 pub mod error;
 
 use crate::lexer::token::{Token, TokenExt};
-use ast::{Ast, Literal, Operation, Operator};
+use crate::source::Span;
+use ast::{Ast, Operation, Operator, Parameter, Program, Signature, TypeExpr, TypeParameter};
+use cst::{Event, NodeKind};
 pub use error::{Error, Result};
 use std::convert::TryInto;
 use std::iter::Peekable;
@@ -11,23 +14,45 @@ use std::iter::Peekable;
 pub struct Parser<S: Iterator<Item = TokenExt>> {
     /// The source [`TokenExt`] iterator
     source: Peekable<S>,
+
+    /// The last consumed token
+    previous: Option<TokenExt>,
+
+    /// Labels of the loops enclosing the current expression, innermost last, each with whether a
+    /// break gives it a value
+    loops: Vec<(Option<String>, bool)>,
+
+    /// Whether the current expression is inside of a function body
+    in_function: bool,
+
+    /// Errors which were recovered from
+    errors: Vec<Error>,
+
+    /// The events of the parse so far, if they are being recorded to build a [`Cst`](cst::Cst)
+    events: Option<Vec<Event>>,
+
+    /// The indices of the start events of the nodes being parsed, innermost last
+    open: Vec<usize>,
 }
 
 macro_rules! la_binary {
     ($name:ident, $( $op:pat )|+, $sub:ident) => {
         fn $name(&mut self) -> Result<Ast> {
+            let marker = self.marker();
             let mut expr = self.$sub()?;
 
             while matches!(
                 self.source.peek().map(|tke| &tke.token),
                 Some($( $op )|+)
             ) {
-                expr = Ast::Operation(
-                    Operation::binary(
-                        Operator::try_from_token_binary(&self.source.next().unwrap().token)?,
-                        (expr, self.$sub()?)
-                    )
+                let operator = self.advance().unwrap();
+                let operation = Operation::binary(
+                    Operator::try_from_token_binary(&operator)?,
+                    (expr, self.$sub()?)
                 );
+                self.wrap(marker, NodeKind::Binary);
+
+                expr = Ast::Operation(operation, operator.lexeme.span);
             }
 
             Ok(expr)
@@ -39,19 +64,129 @@ impl<S: Iterator<Item = TokenExt>> Parser<S> {
     pub fn new(source: S) -> Self {
         Self {
             source: source.peekable(),
+            previous: None,
+            loops: Vec::new(),
+            in_function: false,
+            errors: Vec::new(),
+            events: None,
+            open: Vec::new(),
+        }
+    }
+
+    /// Create a parsing which records its events, from which a [`Cst`](cst::Cst) is built
+    pub fn recording(source: S) -> Self {
+        Self {
+            events: Some(Vec::new()),
+            ..Self::new(source)
         }
     }
 
-    pub fn parse(&mut self) -> Result<Ast> {
-        self.expression()
+    /// Parse the whole source as a program, recovering from as many errors as possible
+    ///
+    /// Returns the program, in which anything that couldn't be parsed is replaced with
+    /// [`Ast::Error`], together with every error encountered.
+    pub fn parse(&mut self) -> (Program, Vec<Error>) {
+        let mut program = Vec::new();
+
+        loop {
+            let (statements, tail) = self.statements(true);
+            program.extend(statements);
+            program.extend(tail);
+
+            // Statements only end early at an unmatched '}'
+            if self.source.peek().is_none() {
+                break;
+            }
+            let err = self.unexpected("';' or end of source");
+            self.errors.push(err);
+            let marker = self.marker();
+            self.advance();
+            self.wrap(marker, NodeKind::Error);
+        }
+
+        (Program(program), std::mem::take(&mut self.errors))
+    }
+
+    /// Parse statements until the end of a block or of the source
+    ///
+    /// Statements are separated by `;`, which may be omitted after a statement ending with a block.
+    /// As in Rust, a statement starting with a block-like expression ends with it, so that it isn't
+    /// continued by an operator or an application on the next line.
+    /// Returns the statements together with the trailing expression, if it isn't followed by `;`.
+    fn statements(&mut self, top_level: bool) -> (Vec<Ast>, Option<Ast>) {
+        let mut statements = Vec::new();
+
+        loop {
+            // Skip empty statements
+            while self.next_is(&Token::Semicolon) {
+                self.advance();
+            }
+            if self.at_block_end() {
+                return (statements, None);
+            }
+
+            let res = if self.next_is(&Token::Interface) || self.next_is(&Token::Instance) {
+                self.global_declaration(top_level)
+            } else if self.next_is_block_like() {
+                self.primary()
+            } else {
+                self.expression()
+            };
+            let recovered = res.is_err();
+            let statement = self.recover(res);
+
+            if self.next_is(&Token::Semicolon) {
+                self.advance();
+            } else if self.at_block_end() {
+                return (statements, Some(statement));
+            } else if !recovered && !self.previous_is(&Token::CurlyRight) {
+                // Report a missing separator and resume at the next statement
+                let err = self.unexpected("';'");
+                self.errors.push(err);
+                self.synchronize();
+            }
+
+            statements.push(statement);
+        }
     }
 
     // Recursive-descent parser
 
     fn expression(&mut self) -> Result<Ast> {
-        self.equality()
+        self.assignment()
     }
 
+    fn assignment(&mut self) -> Result<Ast> {
+        let marker = self.marker();
+        let target = self.range()?;
+
+        match self.advance_if(|tke| tke.token == Token::Equal) {
+            Some(equal) => {
+                // An invalid target doesn't affect the rest of the parse
+                let (name, span) = match target {
+                    Ast::Reference(name, span) => (name, span),
+                    _ => {
+                        self.errors.push(Error::InvalidAssignmentTarget {
+                            span: equal.lexeme.span,
+                        });
+                        (String::new(), equal.lexeme.span)
+                    }
+                };
+
+                let value = self.assignment()?;
+                self.wrap(marker, NodeKind::Assignment);
+
+                Ok(Ast::Assignment {
+                    name,
+                    value: Box::new(value),
+                    span,
+                })
+            }
+            None => Ok(target),
+        }
+    }
+
+    la_binary!(range, Token::DotDot, equality);
     la_binary!(equality, Token::EqualEqual | Token::BangEqual, comparison);
     la_binary!(
         comparison,
@@ -66,126 +201,633 @@ impl<S: Iterator<Item = TokenExt>> Parser<S> {
             self.source.peek().map(|tke| tke.token.clone()),
             Some(Token::Bang | Token::Minus)
         ) {
-            Ok(Ast::Operation(Operation::unary(
-                Operator::try_from_token_unary(&self.source.next().unwrap().token)?,
-                self.unary()?,
-            )))
+            self.node(NodeKind::Unary, |parser| {
+                let operator = parser.advance().unwrap();
+                Ok(Ast::Operation(
+                    Operation::unary(Operator::try_from_token_unary(&operator)?, parser.unary()?),
+                    operator.lexeme.span,
+                ))
+            })
         } else {
             self.apply()
         }
     }
 
     fn apply(&mut self) -> Result<Ast> {
-        let expr = self.primary()?;
-
-        match self.source.peek().map(|tke| &tke.token) {
-            Some(Token::ParenLeft) => {
-                self.source.next();
+        let marker = self.marker();
+        let mut expr = self.primary()?;
 
-                let mut arguments: Vec<Ast> = Vec::new();
-                while let Ok(arg) = self.expression() {
-                    arguments.push(arg);
-                    self.expect(|tke| tke.token == Token::Comma, "argument seperator ','")?;
+        // Functions returned by an application may be applied in turn
+        while let Some(paren) = self.advance_if(|tke| tke.token == Token::ParenLeft) {
+            let mut arguments: Vec<Ast> = Vec::new();
+            while !self.next_is(&Token::ParenRight) {
+                arguments.push(self.expression()?);
+                if self
+                    .expect(|tke| tke.token == Token::Comma, "argument seperator ','")
+                    .is_err()
+                {
+                    break;
                 }
-                self.expect(
-                    |tke| tke.token == Token::ParenRight,
-                    "closing delimiter ')'",
-                )?;
-
-                Ok(Ast::FunctionApplication {
-                    function: Box::new(expr),
-                    arguments,
-                })
             }
-            _ => Ok(expr),
+            self.expect(
+                |tke| tke.token == Token::ParenRight,
+                "closing delimiter ')'",
+            )?;
+            self.wrap(marker, NodeKind::Application);
+
+            expr = Ast::FunctionApplication {
+                function: Box::new(expr),
+                arguments,
+                span: paren.lexeme.span,
+            };
         }
+
+        Ok(expr)
     }
 
     fn primary(&mut self) -> Result<Ast> {
         macro_rules! literal {
             () => {
-                Ok(Ast::Literal(self.source.next().unwrap().token.try_into()?))
+                self.node(NodeKind::Literal, |parser| {
+                    let tke = parser.advance().unwrap();
+                    let span = tke.lexeme.span;
+                    Ok(Ast::Literal(tke.try_into()?, span))
+                })
             };
         }
 
-        match self
-            .source
-            .peek()
-            .ok_or(Error::UnexpectedToken {
-                expected: "literal, operation, grouping, or declaration".into(),
-                found: "end of source".into(),
-            })?
-            .token
-        {
+        let token = match self.source.peek() {
+            Some(tke) => &tke.token,
+            None => return Err(self.unexpected("literal, operation, grouping, or declaration")),
+        };
+
+        match token {
             Token::Nil => literal!(),
             Token::Bool(_) => literal!(),
             Token::Str(_) => literal!(),
             Token::Integer(_) => literal!(),
             Token::Float(_) => literal!(),
 
-            Token::Identifier(_) => Ok(Ast::Reference(self.expect_ident()?)),
-
-            Token::ParenLeft => {
-                self.source.next();
-                let expr = self.expression()?;
-                self.expect(|tke| tke.token == Token::ParenRight, "closing delimiter ')")?;
+            Token::Identifier(_) => self.node(NodeKind::Reference, |parser| {
+                let tke = parser.advance().unwrap();
+                Ok(Ast::Reference(
+                    tke.token.into_identifier().unwrap(),
+                    tke.lexeme.span,
+                ))
+            }),
 
-                Ok(Ast::Grouping(vec![expr]))
-            }
-            Token::CurlyLeft => {
-                self.source.next();
-                let mut exprs = vec![self.expression().unwrap_or(Ast::Literal(Literal::Nil))];
+            Token::ParenLeft => self.node(NodeKind::Paren, |parser| {
+                let paren = parser.advance().unwrap();
+                let expr = parser.expression()?;
+                parser.expect(|tke| tke.token == Token::ParenRight, "closing delimiter ')")?;
 
-                while self
-                    .expect(|tke| tke.token == Token::Semicolon, "semicolon")
-                    .is_ok()
-                {
-                    exprs.push(self.expression()?);
-                }
+                Ok(Ast::Grouping(
+                    Vec::new(),
+                    Some(Box::new(expr)),
+                    paren.lexeme.span,
+                ))
+            }),
+            Token::CurlyLeft => self.block(),
 
-                self.expect(|tke| tke.token == Token::CurlyRight, "closing delimiter '}")?;
+            Token::If => self.node(NodeKind::Conditional, Self::conditional),
 
-                Ok(Ast::Grouping(exprs))
+            Token::Label(_) | Token::While | Token::For => {
+                self.node(NodeKind::While, Self::looping)
             }
+            Token::Break => self.node(NodeKind::Break, Self::jump),
+            Token::Continue => self.node(NodeKind::Continue, Self::jump),
+            Token::Return => self.node(NodeKind::Return, Self::r#return),
 
-            Token::Let => {
-                self.source.next();
+            Token::Let => self.node(NodeKind::Declaration, |parser| {
+                parser.advance();
 
+                let name = parser.expect(
+                    |tke| matches!(tke.token, Token::Identifier(_)),
+                    "identifier",
+                )?;
                 Ok(Ast::Declaration {
-                    name: match self
-                        .expect(
-                            |tke| matches!(tke.token, Token::Identifier(_)),
-                            "identifier",
-                        )?
-                        .token
-                    {
-                        Token::Identifier(ident) => ident,
-                        _ => panic!(),
-                    },
+                    name: name.token.into_identifier().unwrap(),
+                    annotation: parser.annotation()?,
                     value: {
-                        self.expect(|tke| tke.token == Token::Equal, "assignment operator")?;
-                        Box::new(self.expression()?)
+                        parser.expect(|tke| tke.token == Token::Equal, "assignment operator")?;
+                        Box::new(parser.expression()?)
                     },
+                    span: name.lexeme.span,
                 })
+            }),
+            Token::Function => self.node(NodeKind::Function, Self::function),
+            Token::Pipe => self.node(NodeKind::Function, |parser| {
+                let pipe = parser.advance().unwrap();
+
+                let arguments = parser.parameters(
+                    &Token::Pipe,
+                    "argument binding (identifier) or closing delimiter '|'",
+                )?;
+
+                Ok(Ast::Function {
+                    type_parameters: Vec::new(),
+                    arguments,
+                    output: parser.output_annotation()?,
+                    body: Box::new(parser.function_body()?),
+                    span: pipe.lexeme.span,
+                })
+            }),
+
+            // Lexical errors were already reported
+            Token::Error(_) => self.node(NodeKind::Error, |parser| {
+                parser.advance();
+                Ok(Ast::Error)
+            }),
+
+            _ => Err(self.unexpected("expression")),
+        }
+    }
+
+    /// Parse a function, which is declared if it is named
+    fn function(&mut self) -> Result<Ast> {
+        let keyword = self.advance().unwrap();
+
+        // A function without a name is an anonymous function expression
+        let name = if self.next_is(&Token::ParenLeft) || self.next_is(&Token::Less) {
+            None
+        } else {
+            self.retag(NodeKind::Declaration);
+            Some(self.expect(
+                |tke| matches!(tke.token, Token::Identifier(_)),
+                "function name",
+            )?)
+        };
+        let function = self.function_from(keyword.lexeme.span)?;
+
+        Ok(match name {
+            Some(name) => Ast::Declaration {
+                name: name.token.into_identifier().unwrap(),
+                annotation: None,
+                value: Box::new(function),
+                span: name.lexeme.span,
+            },
+            None => function,
+        })
+    }
+
+    /// Parse the rest of a function after its keyword and name, located at its keyword
+    fn function_from(&mut self, span: Span) -> Result<Ast> {
+        let type_parameters = self.type_parameters()?;
+        self.expect(|tke| tke.token == Token::ParenLeft, "opening delimiter '('")?;
+        let arguments = self.parameters(
+            &Token::ParenRight,
+            "argument binding (identifier) or closing delimiter ')'",
+        )?;
+
+        Ok(Ast::Function {
+            type_parameters,
+            arguments,
+            output: self.output_annotation()?,
+            body: Box::new(self.function_body()?),
+            span,
+        })
+    }
+
+    /// Parse an interface or instance declaration, which may only be at the top level
+    fn global_declaration(&mut self, top_level: bool) -> Result<Ast> {
+        let keyword = self.source.peek().unwrap().clone();
+
+        // A misplaced declaration doesn't affect the rest of the parse
+        if !top_level {
+            self.errors.push(Error::NestedDeclaration {
+                keyword: keyword.lexeme.content,
+                span: keyword.lexeme.span,
+            });
+        }
+
+        match keyword.token {
+            Token::Interface => self.node(NodeKind::Interface, Self::interface),
+            Token::Instance => self.node(NodeKind::Instance, Self::instance),
+            _ => unreachable!(),
+        }
+    }
+
+    fn interface(&mut self) -> Result<Ast> {
+        self.advance();
+        let name = self.expect(
+            |tke| matches!(tke.token, Token::Identifier(_)),
+            "interface name",
+        )?;
+
+        self.expect(|tke| tke.token == Token::CurlyLeft, "opening delimiter '{'")?;
+        let mut methods = Vec::new();
+        while !self.next_is(&Token::CurlyRight) {
+            methods.push(self.node(NodeKind::Signature, Self::signature)?);
+            if self.next_is(&Token::CurlyRight) {
+                break;
             }
-            Token::Function => {
-                self.source.next();
-
-                let name = match self
-                    .expect(
-                        |tke| matches!(tke.token, Token::Identifier(_)),
-                        "function name",
-                    )?
-                    .token
-                {
-                    Token::Identifier(val) => val,
-                    _ => unreachable!(),
+            self.expect(|tke| tke.token == Token::Semicolon, "method seperator ';'")?;
+        }
+        self.expect(
+            |tke| tke.token == Token::CurlyRight,
+            "closing delimiter '}'",
+        )?;
+
+        Ok(Ast::Interface {
+            name: name.token.into_identifier().unwrap(),
+            methods,
+            span: name.lexeme.span,
+        })
+    }
+
+    /// Parse the signature of a method, which is a function without a body
+    fn signature(&mut self) -> Result<Signature> {
+        self.expect(
+            |tke| tke.token == Token::Function,
+            "method signature or closing delimiter '}'",
+        )?;
+        let name = self.expect(
+            |tke| matches!(tke.token, Token::Identifier(_)),
+            "method name",
+        )?;
+        self.expect(|tke| tke.token == Token::ParenLeft, "opening delimiter '('")?;
+        let parameters = self.parameters(
+            &Token::ParenRight,
+            "argument binding (identifier) or closing delimiter ')'",
+        )?;
+
+        Ok(Signature {
+            name: name.token.into_identifier().unwrap(),
+            parameters,
+            output: self.output_annotation()?,
+            span: name.lexeme.span,
+        })
+    }
+
+    fn instance(&mut self) -> Result<Ast> {
+        self.advance();
+        let interface = self.expect(
+            |tke| matches!(tke.token, Token::Identifier(_)),
+            "interface name",
+        )?;
+        self.expect(|tke| tke.token == Token::For, "'for'")?;
+        let ty = self.type_expr()?;
+
+        self.expect(|tke| tke.token == Token::CurlyLeft, "opening delimiter '{'")?;
+        let mut methods = Vec::new();
+        while !self.next_is(&Token::CurlyRight) {
+            methods.push(self.node(NodeKind::Declaration, Self::method)?);
+
+            // As between statements, a method ending with a block needn't be followed by `;`
+            if self
+                .advance_if(|tke| tke.token == Token::Semicolon)
+                .is_none()
+                && !self.previous_is(&Token::CurlyRight)
+            {
+                break;
+            }
+        }
+        self.expect(
+            |tke| tke.token == Token::CurlyRight,
+            "closing delimiter '}'",
+        )?;
+
+        Ok(Ast::Instance {
+            interface: interface.token.into_identifier().unwrap(),
+            ty,
+            methods,
+            span: interface.lexeme.span,
+        })
+    }
+
+    /// Parse a method of an instance, which is a named function
+    fn method(&mut self) -> Result<Ast> {
+        let keyword = self.expect(
+            |tke| tke.token == Token::Function,
+            "method or closing delimiter '}'",
+        )?;
+        let name = self.expect(
+            |tke| matches!(tke.token, Token::Identifier(_)),
+            "method name",
+        )?;
+
+        Ok(Ast::Declaration {
+            name: name.token.into_identifier().unwrap(),
+            annotation: None,
+            value: Box::new(self.function_from(keyword.lexeme.span)?),
+            span: name.lexeme.span,
+        })
+    }
+
+    fn block(&mut self) -> Result<Ast> {
+        self.node(NodeKind::Block, |parser| {
+            let curly =
+                parser.expect(|tke| tke.token == Token::CurlyLeft, "opening delimiter '{'")?;
+            let (statements, tail) = parser.statements(false);
+            parser.expect(|tke| tke.token == Token::CurlyRight, "closing delimiter '}")?;
+
+            Ok(Ast::Grouping(
+                statements,
+                tail.map(Box::new),
+                curly.lexeme.span,
+            ))
+        })
+    }
+
+    fn conditional(&mut self) -> Result<Ast> {
+        let keyword = self.expect(|tke| tke.token == Token::If, "'if'")?;
+        let binding = if self.advance_if(|tke| tke.token == Token::Let).is_some() {
+            let name = self.expect_ident()?;
+            self.expect(|tke| tke.token == Token::Equal, "'='")?;
+            Some(name)
+        } else {
+            None
+        };
+        let condition = self.expression()?;
+        let consequence = self.block()?;
+
+        let alternative = if self.advance_if(|tke| tke.token == Token::Else).is_some() {
+            Some(if self.next_is(&Token::If) {
+                self.node(NodeKind::Conditional, Self::conditional)?
+            } else {
+                self.block()?
+            })
+        } else {
+            None
+        };
+
+        Ok(Ast::Conditional {
+            binding,
+            condition: Box::new(condition),
+            consequence: Box::new(consequence),
+            alternative: alternative.map(Box::new),
+            span: keyword.lexeme.span,
+        })
+    }
+
+    fn looping(&mut self) -> Result<Ast> {
+        let label = match self.advance_if(|tke| matches!(tke.token, Token::Label(_))) {
+            Some(tke) => {
+                self.expect(|tke| tke.token == Token::Colon, "':' after loop label")?;
+                tke.token.into_label()
+            }
+            None => None,
+        };
+
+        let keyword = self.expect(
+            |tke| matches!(tke.token, Token::While | Token::For),
+            "'while' or 'for'",
+        )?;
+        if keyword.token == Token::For {
+            self.retag(NodeKind::For);
+        }
+        match keyword.token {
+            Token::While => {
+                let condition = self.expression()?;
+                let (body, valued) = self.loop_body(&label)?;
+
+                Ok(Ast::While {
+                    label,
+                    valued,
+                    condition: Box::new(condition),
+                    body: Box::new(body),
+                    span: keyword.lexeme.span,
+                })
+            }
+            Token::For => {
+                let binding = self.expect_ident()?;
+                self.expect(|tke| tke.token == Token::In, "'in'")?;
+                let iterable = self.expression()?;
+                let (body, valued) = self.loop_body(&label)?;
+
+                Ok(Ast::For {
+                    label,
+                    valued,
+                    binding,
+                    iterable: Box::new(iterable),
+                    body: Box::new(body),
+                    span: keyword.lexeme.span,
+                })
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Parse the body of a loop, returning whether a break in it gives the loop a value
+    fn loop_body(&mut self, label: &Option<String>) -> Result<(Ast, bool)> {
+        self.loops.push((label.clone(), false));
+        let body = self.block();
+        let (_, valued) = self.loops.pop().unwrap();
+
+        Ok((body?, valued))
+    }
+
+    fn jump(&mut self) -> Result<Ast> {
+        let keyword = self.advance().unwrap();
+        let label = self.advance_if(|tke| matches!(tke.token, Token::Label(_)));
+
+        // Misplaced jumps don't affect the rest of the parse, so they needn't be recovered from
+        if self.loops.is_empty() {
+            self.errors.push(Error::OutsideLoop {
+                keyword: keyword.lexeme.content.clone(),
+                span: keyword.lexeme.span,
+            });
+        } else if let Some(label) = &label {
+            let name = label.token.clone().into_label();
+            if !self.loops.iter().any(|(target, _)| target == &name) {
+                self.errors.push(Error::UndefinedLabel {
+                    label: label.lexeme.content.clone(),
+                    span: label.lexeme.span,
+                });
+            }
+        }
+
+        let label = label.and_then(|tke| tke.token.into_label());
+        let span = keyword.lexeme.span;
+        match keyword.token {
+            Token::Break => {
+                let value = if self.next_ends_expression() {
+                    None
+                } else {
+                    Some(Box::new(self.expression()?))
                 };
 
-                self.expect(|tke| tke.token == Token::ParenLeft, "opening delimiter '('")?;
+                if value.is_some() {
+                    let target = match &label {
+                        Some(label) => self
+                            .loops
+                            .iter_mut()
+                            .rev()
+                            .find(|(target, _)| target.as_ref() == Some(label)),
+                        None => self.loops.last_mut(),
+                    };
+                    if let Some((_, valued)) = target {
+                        *valued = true;
+                    }
+                }
+
+                Ok(Ast::Break { label, value, span })
+            }
+            Token::Continue => Ok(Ast::Continue { label, span }),
+            _ => unreachable!(),
+        }
+    }
+
+    fn r#return(&mut self) -> Result<Ast> {
+        let keyword = self.advance().unwrap();
+        if !self.in_function {
+            self.errors.push(Error::OutsideFunction {
+                span: keyword.lexeme.span,
+            });
+        }
+
+        let value = if self.next_ends_expression() {
+            None
+        } else {
+            Some(Box::new(self.expression()?))
+        };
+
+        Ok(Ast::Return {
+            value,
+            span: keyword.lexeme.span,
+        })
+    }
+
+    /// Parse comma-separated argument bindings up to and including the closing delimiter
+    fn parameters(&mut self, close: &Token, expected: &str) -> Result<Vec<Parameter>> {
+        let mut arguments = Vec::new();
+        let mut marker = self.marker();
+        while let Ok(name) = self.expect_ident() {
+            arguments.push(Parameter {
+                name,
+                annotation: self.annotation()?,
+            });
+            self.wrap(marker, NodeKind::Parameter);
+
+            if self
+                .expect(|tke| tke.token == Token::Comma, "argument seperator ','")
+                .is_err()
+            {
+                break;
+            }
+            marker = self.marker();
+        }
+        self.expect(|tke| &tke.token == close, expected)?;
+
+        Ok(arguments)
+    }
+
+    /// Parse the `<`-delimited type parameters of a function, if there are any
+    ///
+    /// Each may be followed by `:` and its bounds, separated by `+`.
+    fn type_parameters(&mut self) -> Result<Vec<TypeParameter>> {
+        let mut type_parameters = Vec::new();
+        if self.advance_if(|tke| tke.token == Token::Less).is_none() {
+            return Ok(type_parameters);
+        }
+
+        let mut marker = self.marker();
+        while let Ok(name) = self.expect_ident() {
+            let mut bounds = Vec::new();
+            if self.advance_if(|tke| tke.token == Token::Colon).is_some() {
+                loop {
+                    let bound =
+                        self.expect(|tke| matches!(tke.token, Token::Identifier(_)), "interface")?;
+                    bounds.push((bound.token.into_identifier().unwrap(), bound.lexeme.span));
+
+                    if self.advance_if(|tke| tke.token == Token::Plus).is_none() {
+                        break;
+                    }
+                }
+            }
+
+            type_parameters.push(TypeParameter { name, bounds });
+            self.wrap(marker, NodeKind::TypeParameter);
+
+            if self
+                .expect(
+                    |tke| tke.token == Token::Comma,
+                    "type parameter seperator ','",
+                )
+                .is_err()
+            {
+                break;
+            }
+            marker = self.marker();
+        }
+        self.expect(
+            |tke| tke.token == Token::Greater,
+            "type parameter (identifier) or closing delimiter '>'",
+        )?;
+
+        Ok(type_parameters)
+    }
+
+    /// Parse a `:` type annotation, if there is one
+    fn annotation(&mut self) -> Result<Option<TypeExpr>> {
+        match self.advance_if(|tke| tke.token == Token::Colon) {
+            Some(_) => Ok(Some(self.type_expr()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Parse a `->` output type annotation, if there is one
+    fn output_annotation(&mut self) -> Result<Option<TypeExpr>> {
+        match self.advance_if(|tke| tke.token == Token::Arrow) {
+            Some(_) => Ok(Some(self.type_expr()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Parse a type, which is optional if followed by `?`
+    fn type_expr(&mut self) -> Result<TypeExpr> {
+        let marker = self.marker();
+        let mut ty = self.required_type_expr()?;
+        self.wrap(marker, NodeKind::Type);
+
+        while let Some(tke) = self.advance_if(|tke| tke.token == Token::Question) {
+            ty = TypeExpr::Named {
+                name: "Option".into(),
+                arguments: vec![ty],
+                span: tke.lexeme.span,
+            };
+            self.wrap(marker, NodeKind::Type);
+        }
+
+        Ok(ty)
+    }
+
+    fn required_type_expr(&mut self) -> Result<TypeExpr> {
+        let tke = self.expect(
+            |tke| matches!(tke.token, Token::Identifier(_) | Token::Function),
+            "type",
+        )?;
+
+        match tke.token {
+            Token::Identifier(name) => {
                 let mut arguments = Vec::new();
-                while let Ok(ident) = self.expect_ident() {
-                    arguments.push(ident);
+                if self.advance_if(|tke| tke.token == Token::Less).is_some() {
+                    while !self.next_is(&Token::Greater) {
+                        arguments.push(self.type_expr()?);
+                        if self
+                            .expect(
+                                |tke| tke.token == Token::Comma,
+                                "type argument seperator ','",
+                            )
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                    self.expect(|tke| tke.token == Token::Greater, "closing delimiter '>'")?;
+                }
+
+                Ok(TypeExpr::Named {
+                    name,
+                    arguments,
+                    span: tke.lexeme.span,
+                })
+            }
+            Token::Function => {
+                self.expect(|tke| tke.token == Token::ParenLeft, "opening delimiter '('")?;
+                let mut parameters = Vec::new();
+                while !self.next_is(&Token::ParenRight) {
+                    parameters.push(self.type_expr()?);
                     if self
                         .expect(|tke| tke.token == Token::Comma, "argument seperator ','")
                         .is_err()
@@ -195,47 +837,731 @@ impl<S: Iterator<Item = TokenExt>> Parser<S> {
                 }
                 self.expect(
                     |tke| tke.token == Token::ParenRight,
-                    "argument binding (identifier) or closing delimiter ')'",
+                    "closing delimiter ')'",
                 )?;
 
-                Ok(Ast::Declaration {
-                    name,
-                    value: Box::new(Ast::Function {
-                        arguments,
-                        body: Box::new(self.expression()?),
-                    }),
+                Ok(TypeExpr::Function {
+                    parameters,
+                    output: self.output_annotation()?.map(Box::new),
+                    span: tke.lexeme.span,
                 })
             }
+            _ => unreachable!(),
+        }
+    }
 
-            _ => Err(Error::UnexpectedToken {
-                expected: "expression".into(),
-                found: format!("'{}'", self.source.next().unwrap().lexeme.content),
-            }),
+    fn function_body(&mut self) -> Result<Ast> {
+        // Loops can't be jumped out of from inside a function
+        let loops = std::mem::take(&mut self.loops);
+        let in_function = std::mem::replace(&mut self.in_function, true);
+        let body = self.expression();
+        self.loops = loops;
+        self.in_function = in_function;
+
+        body
+    }
+
+    // Error recovery
+
+    /// Record the error of a failed parse, if any, and skip to a point where parsing can resume
+    fn recover(&mut self, res: Result<Ast>) -> Ast {
+        res.unwrap_or_else(|err| {
+            self.errors.push(err);
+            self.synchronize();
+
+            Ast::Error
+        })
+    }
+
+    /// Skip tokens until the end of the current expression, which is just before the next `;`,
+    /// `}` or declaration
+    ///
+    /// The skipped tokens are recorded in an error node.
+    fn synchronize(&mut self) {
+        let marker = self.marker();
+        let mut skipped = false;
+        while let Some(tke) = self.source.peek() {
+            if matches!(tke.token, Token::Semicolon | Token::CurlyRight)
+                || self.next_is_declaration()
+            {
+                break;
+            }
+
+            self.advance();
+            skipped = true;
+        }
+
+        if skipped {
+            self.wrap(marker, NodeKind::Error);
+        }
+    }
+
+    // Events
+
+    /// Parse a node of the given kind, which is finished even if the parse fails
+    fn node<T, F>(&mut self, kind: NodeKind, parse: F) -> Result<T>
+    where
+        F: FnOnce(&mut Self) -> Result<T>,
+    {
+        if let Some(events) = &mut self.events {
+            self.open.push(events.len());
+            events.push(Event::Start(kind));
+        }
+
+        let res = parse(self);
+
+        if let Some(events) = &mut self.events {
+            self.open.pop();
+            events.push(Event::Finish);
+        }
+
+        res
+    }
+
+    /// Change the kind of the innermost node being parsed
+    fn retag(&mut self, kind: NodeKind) {
+        if let (Some(events), Some(&start)) = (&mut self.events, self.open.last()) {
+            events[start] = Event::Start(kind);
+        }
+    }
+
+    /// A position in the events, before which a node can later be started with [`Self::wrap`]
+    fn marker(&self) -> usize {
+        self.events.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Wrap everything recorded since the marker in a node of the given kind
+    fn wrap(&mut self, marker: usize, kind: NodeKind) {
+        if let Some(events) = &mut self.events {
+            events.insert(marker, Event::Start(kind));
+            events.push(Event::Finish);
         }
     }
 
     // Helpers
 
-    fn expect<P: FnMut(&TokenExt) -> bool>(&mut self, pred: P, expected: &str) -> Result<TokenExt> {
-        self.source.next_if(pred).ok_or(Error::UnexpectedToken {
+    fn next_is(&mut self, token: &Token) -> bool {
+        self.source.peek().map(|tke| &tke.token) == Some(token)
+    }
+
+    fn previous_is(&self, token: &Token) -> bool {
+        self.previous.as_ref().map(|tke| &tke.token) == Some(token)
+    }
+
+    fn next_ends_expression(&mut self) -> bool {
+        matches!(
+            self.source.peek().map(|tke| &tke.token),
+            None | Some(
+                Token::Semicolon
+                    | Token::Comma
+                    | Token::Else
+                    | Token::ParenRight
+                    | Token::CurlyRight
+                    | Token::SquareRight
+            )
+        )
+    }
+
+    fn at_block_end(&mut self) -> bool {
+        matches!(
+            self.source.peek().map(|tke| &tke.token),
+            None | Some(Token::CurlyRight)
+        )
+    }
+
+    /// Whether the next token starts an expression ending with a block
+    fn next_is_block_like(&mut self) -> bool {
+        matches!(
+            self.source.peek().map(|tke| &tke.token),
+            Some(Token::CurlyLeft | Token::If | Token::While | Token::For | Token::Label(_))
+        )
+    }
+
+    fn next_is_declaration(&mut self) -> bool {
+        matches!(
+            self.source.peek().map(|tke| &tke.token),
+            Some(Token::Let | Token::Function | Token::Interface | Token::Instance)
+        )
+    }
+
+    fn advance(&mut self) -> Option<TokenExt> {
+        let tke = self.source.next()?;
+        self.consume(&tke);
+
+        Some(tke)
+    }
+
+    fn advance_if<P: FnOnce(&TokenExt) -> bool>(&mut self, pred: P) -> Option<TokenExt> {
+        let tke = self.source.next_if(pred)?;
+        self.consume(&tke);
+
+        Some(tke)
+    }
+
+    fn consume(&mut self, tke: &TokenExt) {
+        self.previous = Some(tke.clone());
+        if let Some(events) = &mut self.events {
+            events.push(Event::Token(tke.clone()));
+        }
+    }
+
+    fn expect<P: FnOnce(&TokenExt) -> bool>(
+        &mut self,
+        pred: P,
+        expected: &str,
+    ) -> Result<TokenExt> {
+        match self.advance_if(pred) {
+            Some(tke) => Ok(tke),
+            None => Err(self.unexpected(expected)),
+        }
+    }
+
+    /// An error for the next token, or for the last token at the end of source
+    fn unexpected(&mut self, expected: &str) -> Error {
+        let (found, span) = match self.source.peek() {
+            Some(tke) => (format!("'{}'", tke.lexeme.content), tke.lexeme.span),
+            None => (
+                "end of source".into(),
+                self.previous
+                    .as_ref()
+                    .expect("a token is consumed before the end of source")
+                    .lexeme
+                    .span,
+            ),
+        };
+
+        Error::UnexpectedToken {
             expected: expected.into(),
-            found: match self.source.peek() {
-                Some(tke) => format!("'{}'", tke.lexeme.content),
-                None => "end of source".into(),
-            },
-        })
+            found,
+            span,
+        }
     }
 
     fn expect_ident(&mut self) -> Result<String> {
-        match self
+        Ok(self
             .expect(
                 |tke| matches!(tke.token, Token::Identifier(_)),
                 "identifier",
             )?
             .token
-        {
-            Token::Identifier(val) => Ok(val),
-            _ => unreachable!(),
+            .into_identifier()
+            .unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Analyzer;
+    use crate::source::SourceMap;
+
+    fn parse(source: &str) -> (Vec<Ast>, Vec<Error>) {
+        let file = SourceMap::new().add_repl_input(source.into());
+        let tokens = Analyzer::new(source.chars(), file).map(|tk| tk.unwrap());
+        let (Program(statements), errors) = Parser::new(tokens).parse();
+
+        (statements, errors)
+    }
+
+    #[test]
+    fn parses_function_applications() {
+        for (source, count) in &[("f()", 0), ("f(1)", 1), ("f(1, 2)", 2), ("f(1, 2,)", 2)] {
+            match parse(source) {
+                (statements, errors) if errors.is_empty() => {
+                    let arguments = match &statements[..] {
+                        [Ast::FunctionApplication { arguments, .. }] => arguments,
+                        _ => panic!("{}: {:?}", source, statements),
+                    };
+                    assert_eq!(arguments.len(), *count, "{}", source)
+                }
+                (_, errors) => panic!("{}: {:?}", source, errors),
+            }
+        }
+    }
+
+    #[test]
+    fn parses_functions() {
+        let arity = |source| match parse(source) {
+            (statements, errors) if errors.is_empty() => match &statements[..] {
+                [Ast::Function { arguments, .. }] => Some(arguments.len()),
+                [Ast::Declaration { value, .. }] => match &**value {
+                    Ast::Function { .. } => None,
+                    value => panic!("{}: {:?}", source, value),
+                },
+                _ => panic!("{}: {:?}", source, statements),
+            },
+            (_, errors) => panic!("{}: {:?}", source, errors),
+        };
+
+        assert_eq!(arity("fn(x) x + 1"), Some(1));
+        assert_eq!(arity("fn() {}"), Some(0));
+        assert_eq!(arity("|x, y| x + y"), Some(2));
+        assert_eq!(arity("|| 1"), Some(0));
+        assert_eq!(arity("fn named(x) x"), None);
+
+        assert_eq!(parse("|x x").1.len(), 1);
+        assert_eq!(parse("fn(x").1.len(), 1);
+
+        match &parse("f(1)(2, 3)").0[..] {
+            [Ast::FunctionApplication {
+                function,
+                arguments,
+                ..
+            }] => {
+                assert_eq!(arguments.len(), 2);
+                assert!(matches!(**function, Ast::FunctionApplication { .. }));
+            }
+            statements => panic!("{:?}", statements),
+        }
+    }
+
+    #[test]
+    fn parses_programs() {
+        for (source, count) in &[
+            ("", 0),
+            (";;", 0),
+            ("let a = 1; let b = 2", 2),
+            ("a; b;", 2),
+        ] {
+            let (statements, errors) = parse(source);
+            assert_eq!(errors, [], "{}", source);
+            assert_eq!(statements.len(), *count, "{}", source);
+        }
+
+        let (statements, errors) = parse("let a = 1 2; a } b");
+        assert_eq!(statements.len(), 3);
+        assert_eq!(
+            errors
+                .iter()
+                .map(|err| err.span().range())
+                .collect::<Vec<_>>(),
+            vec![10..11, 15..16]
+        );
+    }
+
+    #[test]
+    fn locates_errors() {
+        let spans = |source| {
+            parse(source)
+                .1
+                .iter()
+                .map(|err| err.span().range())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(spans("(1 + x"), vec![5..6]);
+        assert_eq!(spans("{ let = 1; (2 ] }"), vec![6..7, 14..15]);
+        assert_eq!(spans("f(1, \"two\""), vec![5..10]);
+    }
+
+    #[test]
+    fn parses_blocks() {
+        let block = |source| match parse(source) {
+            (statements, errors) if errors.is_empty() => match &statements[..] {
+                [Ast::Grouping(statements, tail, _)] => (statements.len(), tail.is_some()),
+                _ => panic!("{}: {:?}", source, statements),
+            },
+            (_, errors) => panic!("{}: {:?}", source, errors),
+        };
+
+        assert_eq!(block("{}"), (0, false));
+        assert_eq!(block("{ x }"), (0, true));
+        assert_eq!(block("{ let x = 1; x; }"), (2, false));
+        assert_eq!(block("{ ;; x ;; y }"), (1, true));
+        assert_eq!(block("{ { x } fn f() { y } z }"), (2, true));
+
+        assert_eq!(parse("{ x y }").1.len(), 1);
+    }
+
+    #[test]
+    fn ends_statements_at_blocks() {
+        let statements = |source| match parse(source) {
+            (statements, errors) if errors.is_empty() => statements,
+            (_, errors) => panic!("{}: {:?}", source, errors),
+        };
+
+        assert!(matches!(
+            &statements("{ a } (2)")[..],
+            [Ast::Grouping(..), Ast::Grouping(..)]
+        ));
+        assert!(matches!(
+            &statements("while false {} -1")[..],
+            [Ast::While { .. }, Ast::Operation(..)]
+        ));
+        assert!(matches!(
+            &statements("'a: for i in 0..1 {} (f)(1)")[..],
+            [Ast::For { .. }, Ast::FunctionApplication { .. }]
+        ));
+        assert!(matches!(
+            &statements("if c { 1 } else { 2 }\n-x")[..],
+            [Ast::Conditional { .. }, Ast::Operation(..)]
+        ));
+
+        // Only at the start of a statement
+        assert!(matches!(
+            &statements("let a = { 1 } + 2")[..],
+            [Ast::Declaration { .. }]
+        ));
+        match &statements("fn f(x) { if x < 0 { return 0 }\n(x + 1) * 2 }")[..] {
+            [Ast::Declaration { value, .. }] => match &**value {
+                Ast::Function { body, .. } => assert!(matches!(
+                    &**body,
+                    Ast::Grouping(statements, Some(_), _) if statements.len() == 1
+                )),
+                value => panic!("{:?}", value),
+            },
+            statements => panic!("{:?}", statements),
+        }
+    }
+
+    #[test]
+    fn parses_conditionals() {
+        let (statements, errors) = parse("if a { b } else if c { d }");
+        assert_eq!(errors, []);
+        match &statements[..] {
+            [Ast::Conditional {
+                alternative: Some(alternative),
+                ..
+            }] => assert!(matches!(
+                **alternative,
+                Ast::Conditional {
+                    alternative: None,
+                    ..
+                }
+            )),
+            statements => panic!("{:?}", statements),
+        }
+
+        let (statements, errors) = parse("if let x = a { x } else if let y = b { y }");
+        assert_eq!(errors, []);
+        assert!(matches!(
+            &statements[..],
+            [Ast::Conditional {
+                binding: Some(binding),
+                condition,
+                ..
+            }] if binding == "x" && matches!(**condition, Ast::Reference(..))
+        ));
+
+        assert_eq!(parse("if a b").1.len(), 1);
+        assert_eq!(parse("if a { b } else c").1.len(), 1);
+        assert!(matches!(
+            &parse("if let a { b }").1[0],
+            Error::UnexpectedToken { expected, .. } if expected == "'='"
+        ));
+    }
+
+    #[test]
+    fn checks_jumps() {
+        let errors = |source| parse(source).1;
+
+        assert_eq!(errors("while a { break }; for x in y { continue }"), []);
+        assert_eq!(errors("'a: while b { for c in d { continue 'a } }"), []);
+        assert_eq!(errors("'a: while b { break 'a c }"), []);
+
+        assert!(matches!(&errors("break")[..], [Error::OutsideLoop { .. }]));
+        assert!(matches!(
+            &errors("while a { fn f() continue }")[..],
+            [Error::OutsideLoop { .. }]
+        ));
+        assert!(matches!(
+            &errors("'a: while b { break 'c }")[..],
+            [Error::UndefinedLabel { label, .. }] if label == "'c"
+        ));
+        assert!(matches!(
+            &errors("'a: while b {}; while c { continue 'a }")[..],
+            [Error::UndefinedLabel { .. }]
+        ));
+    }
+
+    #[test]
+    fn checks_returns() {
+        let errors = |source| parse(source).1;
+
+        assert_eq!(
+            errors("fn f() return; fn g(x) { while x { return x } }"),
+            []
+        );
+
+        assert!(matches!(
+            &errors("return 1")[..],
+            [Error::OutsideFunction { .. }]
+        ));
+        assert!(matches!(
+            &errors("fn f() {}; while true { return }")[..],
+            [Error::OutsideFunction { .. }]
+        ));
+    }
+
+    #[test]
+    fn parses_assignments() {
+        let (statements, errors) = parse("a = b = 1");
+        assert_eq!(errors, []);
+        match &statements[..] {
+            [Ast::Assignment { name, value, .. }] => {
+                assert_eq!(name, "a");
+                assert!(matches!(**value, Ast::Assignment { .. }));
+            }
+            statements => panic!("{:?}", statements),
+        }
+
+        assert!(matches!(
+            &parse("1 = 2; f() = 3").1[..],
+            [
+                Error::InvalidAssignmentTarget { .. },
+                Error::InvalidAssignmentTarget { .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn parses_type_parameters() {
+        for (source, expected) in &[
+            ("fn id<T>(x: T) -> T x", vec!["T"]),
+            ("fn<A, B,>(f: fn(A) -> B) f", vec!["A", "B"]),
+            ("fn() {}", vec![]),
+        ] {
+            let (statements, errors) = parse(source);
+            assert_eq!(errors, [], "{}", source);
+            let function = match &statements[..] {
+                [Ast::Declaration { value, .. }] => &**value,
+                [function] => function,
+                statements => panic!("{}: {:?}", source, statements),
+            };
+            match function {
+                Ast::Function {
+                    type_parameters, ..
+                } => assert_eq!(
+                    type_parameters
+                        .iter()
+                        .map(|param| param.name.as_str())
+                        .collect::<Vec<_>>(),
+                    *expected,
+                    "{}",
+                    source
+                ),
+                function => panic!("{}: {:?}", source, function),
+            }
+        }
+
+        match &parse("let x: Pair<T, fn(T) -> Integer> = y").0[..] {
+            [Ast::Declaration {
+                annotation:
+                    Some(TypeExpr::Named {
+                        name, arguments, ..
+                    }),
+                ..
+            }] => {
+                assert_eq!(name, "Pair");
+                assert!(matches!(
+                    &arguments[..],
+                    [TypeExpr::Named { .. }, TypeExpr::Function { .. }]
+                ));
+            }
+            statements => panic!("{:?}", statements),
+        }
+
+        match &parse("fn<T: Eq + Show, U: Ord>() {}").0[..] {
+            [Ast::Function {
+                type_parameters, ..
+            }] => assert_eq!(
+                type_parameters
+                    .iter()
+                    .map(|param| param
+                        .bounds
+                        .iter()
+                        .map(|(bound, _)| bound.as_str())
+                        .collect())
+                    .collect::<Vec<Vec<_>>>(),
+                vec![vec!["Eq", "Show"], vec!["Ord"]]
+            ),
+            statements => panic!("{:?}", statements),
+        }
+
+        assert_eq!(parse("fn f<1>(x) x").1.len(), 1);
+        assert_eq!(parse("fn f<T: Eq +>(x) x").1.len(), 1);
+        assert_eq!(parse("let x: Pair<T = y").1.len(), 1);
+    }
+
+    #[test]
+    fn parses_annotations() {
+        let named = |expr: &TypeExpr| match expr {
+            TypeExpr::Named { name, .. } => name.clone(),
+            expr => panic!("{:?}", expr),
+        };
+
+        let (statements, errors) = parse("let x: Integer = 1");
+        assert_eq!(errors, []);
+        match &statements[..] {
+            [Ast::Declaration {
+                annotation: Some(annotation),
+                ..
+            }] => assert_eq!(named(annotation), "Integer"),
+            statements => panic!("{:?}", statements),
+        }
+
+        for source in &[
+            "fn f(a: Integer, b) -> Bool a == b",
+            "fn(a: Integer, b) -> Bool a == b",
+            "|a: Integer, b| -> Bool a == b",
+        ] {
+            let (statements, errors) = parse(source);
+            assert_eq!(errors, [], "{}", source);
+            let function = match &statements[..] {
+                [Ast::Declaration { value, .. }] => &**value,
+                [function] => function,
+                statements => panic!("{}: {:?}", source, statements),
+            };
+            match function {
+                Ast::Function {
+                    arguments,
+                    output: Some(output),
+                    ..
+                } => {
+                    assert_eq!(
+                        arguments
+                            .iter()
+                            .map(|param| param.annotation.as_ref().map(named))
+                            .collect::<Vec<_>>(),
+                        vec![Some("Integer".into()), None],
+                        "{}",
+                        source
+                    );
+                    assert_eq!(named(output), "Bool", "{}", source);
+                }
+                function => panic!("{}: {:?}", source, function),
+            }
+        }
+
+        match &parse("let f: fn(Integer, fn() -> Bool) = g").0[..] {
+            [Ast::Declaration {
+                annotation:
+                    Some(TypeExpr::Function {
+                        parameters,
+                        output: None,
+                        ..
+                    }),
+                ..
+            }] => {
+                assert_eq!(named(&parameters[0]), "Integer");
+                assert!(matches!(
+                    &parameters[1],
+                    TypeExpr::Function { parameters, output: Some(_), .. } if parameters.is_empty()
+                ));
+            }
+            statements => panic!("{:?}", statements),
+        }
+
+        match &parse("let x: Integer?? = y").0[..] {
+            [Ast::Declaration {
+                annotation:
+                    Some(TypeExpr::Named {
+                        name, arguments, ..
+                    }),
+                ..
+            }] => {
+                assert_eq!(name, "Option");
+                assert!(matches!(
+                    &arguments[..],
+                    [TypeExpr::Named { name, arguments, .. }]
+                        if name == "Option" && named(&arguments[0]) == "Integer"
+                ));
+            }
+            statements => panic!("{:?}", statements),
+        }
+
+        for source in &[
+            "let x: = 1",
+            "fn(x) -> 1",
+            "|x: fn(| x",
+            "let x: ?Integer = y",
+        ] {
+            assert!(
+                matches!(
+                    &parse(source).1[..],
+                    [Error::UnexpectedToken { expected, .. }] if expected == "type"
+                ),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn parses_interfaces_and_instances() {
+        let (statements, errors) = parse(
+            "interface Describe { fn describe(x: Self) -> String; fn size(x: Self) -> Integer; }
+            instance Describe for Bool { fn describe(b) \"bool\"; fn size(b) { 1 } }
+            describe(true)",
+        );
+        assert_eq!(errors, []);
+        match &statements[..] {
+            [Ast::Interface { name, methods, .. }, Ast::Instance {
+                interface,
+                ty: TypeExpr::Named { name: ty, .. },
+                methods: definitions,
+                ..
+            }, Ast::FunctionApplication { .. }] => {
+                assert_eq!(name, "Describe");
+                assert_eq!(
+                    methods
+                        .iter()
+                        .map(|sig| sig.name.as_str())
+                        .collect::<Vec<_>>(),
+                    ["describe", "size"]
+                );
+                assert!(methods.iter().all(|sig| sig.parameters.len() == 1));
+                assert_eq!((interface.as_str(), ty.as_str()), ("Describe", "Bool"));
+                assert!(definitions.iter().all(|method| matches!(
+                    method,
+                    Ast::Declaration { value, .. } if matches!(**value, Ast::Function { .. })
+                )));
+            }
+            statements => panic!("{:?}", statements),
+        }
+
+        assert_eq!(
+            parse("interface Empty {}; instance Empty for Unit {}").1,
+            []
+        );
+        assert_eq!(
+            parse("interface Eq { fn eq(a: Self, b: Self) -> Bool }").1,
+            []
+        );
+        let expected = |source| match &parse(source).1[..] {
+            [Error::UnexpectedToken { expected, .. }, ..] => expected.clone(),
+            errors => panic!("{}: {:?}", source, errors),
+        };
+        assert_eq!(
+            expected("interface Eq { fn eq(a: Self) x }"),
+            "method seperator ';'"
+        );
+        assert_eq!(expected("instance Eq Bool {}"), "'for'");
+        assert_eq!(
+            expected("instance Eq for Bool { let eq = 1 }"),
+            "method or closing delimiter '}'"
+        );
+        assert!(matches!(
+            &parse("fn f() { interface Eq {}; 1 }").1[..],
+            [Error::NestedDeclaration { keyword, .. }] if keyword == "interface"
+        ));
+    }
+
+    #[test]
+    fn recovers_from_errors() {
+        let (statements, errors) = parse("{ let = 1; 2 + ; let y = 3 let z = ) ; z }");
+
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        match &statements[..] {
+            [Ast::Grouping(statements, Some(tail), _)] => {
+                assert_eq!(statements.len(), 4);
+                assert_eq!(statements[0], Ast::Error);
+                assert_eq!(statements[1], Ast::Error);
+                assert!(matches!(statements[2], Ast::Declaration { .. }));
+                assert_eq!(statements[3], Ast::Error);
+                assert!(matches!(&**tail, Ast::Reference(name, _) if name == "z"));
+            }
+            statements => panic!("{:?}", statements),
         }
     }
 }
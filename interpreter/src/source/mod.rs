@@ -0,0 +1,252 @@
+pub mod span;
+
+pub use span::Span;
+use std::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
+
+/// A collection of all source files known to the interpreter
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+
+    /// The number of REPL inputs which have been registered
+    repl_inputs: usize,
+}
+
+/// A small handle to a [`SourceFile`] in a [`SourceMap`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId(usize);
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a file, returning its [`FileId`]
+    pub fn add_file(&mut self, name: FileName, content: String) -> FileId {
+        let mut file = SourceFile::new(name);
+        content.chars().for_each(|ch| file.push(ch));
+        file.content = Some(content);
+
+        self.files.push(file);
+        FileId(self.files.len() - 1)
+    }
+
+    /// Register a REPL input as a numbered virtual file, returning its [`FileId`]
+    pub fn add_repl_input(&mut self, content: String) -> FileId {
+        self.repl_inputs += 1;
+        self.add_file(FileName::Repl(self.repl_inputs), content)
+    }
+
+    /// Register a file whose content will be streamed through [`SourceMap::record`]
+    pub fn add_stream(&mut self, name: FileName) -> FileId {
+        self.files.push(SourceFile::new(name));
+        FileId(self.files.len() - 1)
+    }
+
+    /// Wrap a character stream so that the positions of lines in a file are recorded as they are
+    /// read, which keeps the file available for diagnostics without keeping its content
+    pub fn record<I>(&mut self, id: FileId, chars: I) -> Recording<'_, I>
+    where
+        I: Iterator<Item = char>,
+    {
+        Recording {
+            chars,
+            file: &mut self.files[id.0],
+        }
+    }
+
+    pub fn get(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0]
+    }
+
+    /// Resolve the start of a [`Span`] to a human-readable [`Location`]
+    pub fn location(&self, span: Span) -> Location<'_> {
+        let file = self.get(span.file);
+        let (line, column) = file.line_column(span.start);
+
+        Location {
+            name: &file.name,
+            line,
+            column,
+        }
+    }
+}
+
+/// The name of a [`SourceFile`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileName {
+    /// A file on disk
+    Path(PathBuf),
+
+    /// The nth input to the REPL (starting from one)
+    Repl(usize),
+
+    /// The standard input stream
+    Stdin,
+}
+
+impl Display for FileName {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Path(path) => write!(f, "{}", path.display()),
+            Self::Repl(n) => write!(f, "<repl:{}>", n),
+            Self::Stdin => write!(f, "<stdin>"),
+        }
+    }
+}
+
+/// A source file, and its contents unless it was streamed
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    name: FileName,
+    content: Option<String>,
+
+    /// The length of the content in bytes
+    len: usize,
+
+    /// Byte offsets of the start of each line
+    line_starts: Vec<usize>,
+
+    /// Byte offsets and lengths of each character longer than one byte, by which columns are
+    /// counted in characters
+    multibyte_chars: Vec<(usize, usize)>,
+}
+
+impl SourceFile {
+    fn new(name: FileName) -> Self {
+        Self {
+            name,
+            content: None,
+            len: 0,
+            line_starts: vec![0],
+            multibyte_chars: Vec::new(),
+        }
+    }
+
+    pub fn name(&self) -> &FileName {
+        &self.name
+    }
+
+    /// The content of the file, which isn't kept if it was streamed
+    pub fn content(&self) -> Option<&str> {
+        self.content.as_deref()
+    }
+
+    /// Record the position of a character following the content so far
+    fn push(&mut self, ch: char) {
+        if ch.len_utf8() > 1 {
+            self.multibyte_chars.push((self.len, ch.len_utf8()));
+        }
+        self.len += ch.len_utf8();
+
+        if ch == '\n' {
+            self.line_starts.push(self.len);
+        }
+    }
+
+    /// Convert a byte offset into zero-based line and column numbers
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let start = self.line_starts[line];
+
+        // Each multibyte character before the offset takes up more than one byte of the line
+        let first = self
+            .multibyte_chars
+            .partition_point(|(pos, _)| *pos < start);
+        let extra: usize = self.multibyte_chars[first..]
+            .iter()
+            .take_while(|(pos, _)| *pos < offset)
+            .map(|(_, len)| len - 1)
+            .sum();
+
+        (line, offset - start - extra)
+    }
+}
+
+/// A character stream which is recorded into a [`SourceFile`] as it is read
+pub struct Recording<'a, I> {
+    chars: I,
+    file: &'a mut SourceFile,
+}
+
+impl<I: Iterator<Item = char>> Iterator for Recording<'_, I> {
+    type Item = char;
+    fn next(&mut self) -> Option<Self::Item> {
+        let ch = self.chars.next()?;
+        self.file.push(ch);
+
+        Some(ch)
+    }
+}
+
+/// A human-readable location in a [`SourceFile`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location<'a> {
+    pub name: &'a FileName,
+
+    /// Zero-based line number
+    pub line: usize,
+
+    /// Zero-based column number, counted in characters
+    pub column: usize,
+}
+
+impl Display for Location<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.name, self.line + 1, self.column + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_line_and_column() {
+        let mut map = SourceMap::new();
+        let file = map.add_file(
+            FileName::Path("test.smk".into()),
+            "let a = 1;\n\nlet b = 2;\n".into(),
+        );
+
+        let location = |start| map.location(Span::new(file, start, start + 1));
+        assert_eq!((location(0).line, location(0).column), (0, 0));
+        assert_eq!((location(9).line, location(9).column), (0, 9));
+        assert_eq!((location(11).line, location(11).column), (1, 0));
+        assert_eq!((location(16).line, location(16).column), (2, 4));
+        assert_eq!(location(16).to_string(), "test.smk:3:5");
+    }
+
+    #[test]
+    fn records_streamed_content() {
+        let mut map = SourceMap::new();
+        let file = map.add_stream(FileName::Stdin);
+
+        let streamed: String = map.record(file, "first\nsëcond".chars()).collect();
+        assert_eq!(streamed, "first\nsëcond");
+        assert_eq!(map.get(file).content(), None);
+        assert_eq!(
+            map.location(Span::new(file, 7, 9)).to_string(),
+            "<stdin>:2:2"
+        );
+        assert_eq!(
+            map.location(Span::new(file, 9, 10)).to_string(),
+            "<stdin>:2:3"
+        );
+    }
+
+    #[test]
+    fn numbers_repl_inputs() {
+        let mut map = SourceMap::new();
+        let first = map.add_repl_input("1".into());
+        let second = map.add_repl_input("2".into());
+
+        assert_ne!(first, second);
+        assert_eq!(map.get(first).name(), &FileName::Repl(1));
+        assert_eq!(map.get(second).name().to_string(), "<repl:2>");
+    }
+}
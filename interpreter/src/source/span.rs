@@ -0,0 +1,25 @@
+use super::FileId;
+use std::ops::Range;
+
+/// A range of bytes in a source file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub file: FileId,
+
+    /// Byte offset of the start of the span
+    pub start: usize,
+
+    /// Byte offset of the end of the span (exclusive)
+    pub end: usize,
+}
+
+impl Span {
+    pub const fn new(file: FileId, start: usize, end: usize) -> Self {
+        Self { file, start, end }
+    }
+
+    #[allow(dead_code)]
+    pub const fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
@@ -0,0 +1,231 @@
+use super::{Interface, Type};
+use crate::source::Span;
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A syntax tree whose type differs from the one required by its context
+    Mismatch {
+        expected: Type,
+        found: Type,
+        span: Span,
+    },
+
+    UndefinedReference {
+        name: String,
+        span: Span,
+    },
+
+    UnknownType {
+        name: String,
+        span: Span,
+    },
+
+    /// A type which doesn't implement an interface required by its context, such as that of an
+    /// operator
+    Unimplemented {
+        interface: Interface,
+        found: Type,
+        span: Span,
+    },
+
+    UnknownInterface {
+        name: String,
+        span: Span,
+    },
+
+    DuplicateInterface {
+        name: String,
+        span: Span,
+    },
+
+    /// A method declared by more than one interface, or implemented more than once by an instance
+    DuplicateMethod {
+        name: String,
+        span: Span,
+    },
+
+    /// A parameter of a method signature without a type annotation
+    UnannotatedParameter {
+        method: String,
+        name: String,
+        span: Span,
+    },
+
+    /// A method signature whose first parameter isn't `Self`, by which the method is dispatched
+    MethodWithoutSelf {
+        name: String,
+        span: Span,
+    },
+
+    /// An instance for a type which has type arguments, or isn't named
+    InvalidInstance {
+        found: Type,
+        span: Span,
+    },
+
+    DuplicateInstance {
+        interface: Interface,
+        found: Type,
+        span: Span,
+    },
+
+    UnknownMethod {
+        interface: Interface,
+        name: String,
+        span: Span,
+    },
+
+    /// An instance which doesn't implement every method of its interface
+    MissingMethod {
+        interface: Interface,
+        name: String,
+        span: Span,
+    },
+
+    /// A named type applied to the wrong number of type arguments
+    TypeArity {
+        name: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+
+    /// An application of something other than a function, located at the callee
+    NotCallable {
+        found: Type,
+        span: Span,
+    },
+
+    /// An application with the wrong number of arguments, located at the `(`
+    ArityMismatch {
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+
+    /// An `if` without `else` whose consequence isn't unit
+    MissingAlternative {
+        found: Type,
+        span: Span,
+    },
+
+    /// An assignment to a binding of a polymorphic type, which may only be declared
+    PolymorphicAssignment {
+        name: String,
+        span: Span,
+    },
+
+    NotIterable {
+        found: Type,
+        span: Span,
+    },
+}
+
+impl Error {
+    /// The span of source at which the error was encountered
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Mismatch { span, .. }
+            | Self::UndefinedReference { span, .. }
+            | Self::UnknownType { span, .. }
+            | Self::Unimplemented { span, .. }
+            | Self::UnknownInterface { span, .. }
+            | Self::DuplicateInterface { span, .. }
+            | Self::DuplicateMethod { span, .. }
+            | Self::UnannotatedParameter { span, .. }
+            | Self::MethodWithoutSelf { span, .. }
+            | Self::InvalidInstance { span, .. }
+            | Self::DuplicateInstance { span, .. }
+            | Self::UnknownMethod { span, .. }
+            | Self::MissingMethod { span, .. }
+            | Self::TypeArity { span, .. }
+            | Self::NotCallable { span, .. }
+            | Self::ArityMismatch { span, .. }
+            | Self::MissingAlternative { span, .. }
+            | Self::PolymorphicAssignment { span, .. }
+            | Self::NotIterable { span, .. } => *span,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Mismatch {
+                expected, found, ..
+            } => write!(f, "expected {} but found {}", expected, found),
+            Self::UndefinedReference { name, .. } => write!(f, "{} is not defined", name),
+            Self::UnknownType { name, .. } => write!(f, "unknown type {}", name),
+            Self::Unimplemented {
+                interface, found, ..
+            } => write!(f, "{} does not implement {}", found, interface),
+            Self::UnknownInterface { name, .. } => write!(f, "unknown interface {}", name),
+            Self::DuplicateInterface { name, .. } => {
+                write!(f, "interface {} is already declared", name)
+            }
+            Self::DuplicateMethod { name, .. } => write!(f, "method {} is already declared", name),
+            Self::UnannotatedParameter { method, name, .. } => write!(
+                f,
+                "parameter {} of method {} must be annotated",
+                name, method
+            ),
+            Self::MethodWithoutSelf { name, .. } => write!(
+                f,
+                "the first parameter of method {} must be of type Self",
+                name
+            ),
+            Self::InvalidInstance { found, .. } => write!(
+                f,
+                "instances can only be declared for named types without type arguments, not {}",
+                found
+            ),
+            Self::DuplicateInstance {
+                interface, found, ..
+            } => write!(f, "{} already implements {}", found, interface),
+            Self::UnknownMethod {
+                interface, name, ..
+            } => write!(f, "{} is not a method of {}", name, interface),
+            Self::MissingMethod {
+                interface, name, ..
+            } => write!(f, "instance of {} is missing method {}", interface, name),
+            Self::TypeArity {
+                name,
+                expected,
+                found,
+                ..
+            } => write!(
+                f,
+                "{} takes {} type arguments but found {}",
+                name, expected, found
+            ),
+            Self::NotCallable { found, .. } => {
+                write!(f, "expected a function but found {}", found)
+            }
+            Self::ArityMismatch {
+                expected, found, ..
+            } => write!(f, "expected {} arguments but found {}", expected, found),
+            Self::MissingAlternative { found, .. } => write!(
+                f,
+                "'if' without 'else' must be Unit, but its consequence is {}",
+                found
+            ),
+            Self::PolymorphicAssignment { name, .. } => {
+                write!(
+                    f,
+                    "{} has a polymorphic type, so it can't be assigned to",
+                    name
+                )
+            }
+            Self::NotIterable { found, .. } => {
+                write!(
+                    f,
+                    "expected an iterable (Range or String) but found {}",
+                    found
+                )
+            }
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
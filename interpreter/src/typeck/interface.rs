@@ -0,0 +1,73 @@
+use super::Type;
+use std::fmt::{self, Display, Formatter};
+
+/// A set of methods which a type may support, by having an instance of the interface
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interface(pub String);
+
+impl Interface {
+    pub fn named(name: &str) -> Self {
+        Self(name.into())
+    }
+}
+
+/// The builtin interfaces, with the names of their methods
+pub const BUILTINS: &[(&str, &[&str])] = &[
+    // Equality, by `==` and `!=`
+    ("Eq", &["eq"]),
+    // Ordering, by `<`, `<=`, `>` and `>=`
+    ("Ord", &["lt"]),
+    // Addition or concatenation, by `+`
+    ("Add", &["add"]),
+    // Arithmetic, by `-`, `*`, `/` and negation
+    ("Num", &["sub", "mul", "div", "neg"]),
+    // Conversion to a string
+    ("Show", &["show"]),
+];
+
+/// The builtin instances of each builtin interface, by the name of the type implementing it
+///
+/// A type with type arguments only implements an interface if its arguments do too.
+const INSTANCES: &[(&str, &[&str])] = &[
+    (
+        "Eq",
+        &[
+            "Unit", "Bool", "Integer", "Float", "String", "Range", "Option",
+        ],
+    ),
+    ("Ord", &["Integer", "Float", "String"]),
+    ("Add", &["Integer", "Float", "String"]),
+    ("Num", &["Integer", "Float"]),
+    (
+        "Show",
+        &[
+            "Unit", "Bool", "Integer", "Float", "String", "Range", "Option",
+        ],
+    ),
+];
+
+/// The type of a method of a builtin interface, given the type implementing it
+pub fn builtin_method(method: &str, this: &Type) -> Type {
+    let function = |parameters, output| Type::Function(parameters, Box::new(output));
+    match method {
+        "eq" | "lt" => function(vec![this.clone(), this.clone()], Type::named("Bool")),
+        "add" | "sub" | "mul" | "div" => function(vec![this.clone(), this.clone()], this.clone()),
+        "neg" => function(vec![this.clone()], this.clone()),
+        "show" => function(vec![this.clone()], Type::named("String")),
+        _ => unreachable!("{} is not a builtin method", method),
+    }
+}
+
+/// Whether the builtin type with the given name has a builtin instance of the interface, given
+/// that its type arguments do
+pub fn builtin_instance(interface: &Interface, ty: &str) -> bool {
+    INSTANCES
+        .iter()
+        .any(|(implemented, types)| *implemented == interface.0 && types.contains(&ty))
+}
+
+impl Display for Interface {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
@@ -0,0 +1,1532 @@
+mod error;
+mod interface;
+mod types;
+
+use crate::parser::ast::{Ast, Literal, Operation, Parameter, Program, Signature, TypeExpr};
+use crate::source::Span;
+pub use error::{Error, Result};
+pub use interface::Interface;
+use std::collections::{BTreeSet, HashMap, HashSet};
+pub use types::{Scheme, Type};
+
+/// A Hindley-Milner type checker, inferring the types of programs before they are run
+///
+/// Declarations are generalized, so a function like `|x| x` can be used at any type. Operators
+/// apply the methods of builtin [`Interface`]s, which variables may be required to implement.
+/// Global declarations, interfaces and instances persist between checked programs, like they do in
+/// the interpreter.
+pub struct Checker {
+    /// Scopes of bindings, the global scope first and the innermost last
+    scopes: Vec<HashMap<String, Scheme>>,
+
+    /// Every type variable, each of which is either bound to a type or still unknown
+    variables: Vec<Variable>,
+
+    /// The output types of the enclosing functions, innermost last
+    outputs: Vec<Type>,
+
+    /// The labels of the loops enclosing the current expression with the types of their break
+    /// values, innermost last
+    loops: Vec<(Option<String>, Type)>,
+
+    /// The type parameters of the enclosing functions with their variables, innermost last
+    type_parameters: Vec<(String, usize)>,
+
+    /// The declared interfaces, with the names of their methods
+    interface_methods: HashMap<Interface, Vec<String>>,
+
+    /// The methods of every interface, with the interface declaring each and its type, which is
+    /// generalized over the type implementing it first
+    methods: HashMap<String, (Interface, Scheme)>,
+
+    /// The types with a declared instance of each interface, besides the builtin instances
+    instances: HashSet<(Interface, String)>,
+}
+
+#[derive(Debug, Clone)]
+enum Variable {
+    /// A variable which may still be unified with any type implementing its interfaces
+    Unbound {
+        interfaces: BTreeSet<Interface>,
+    },
+
+    /// A type parameter of a function being checked, which may only be unified with itself, and
+    /// only implements the interfaces bounding it
+    Rigid {
+        name: String,
+        bounds: BTreeSet<Interface>,
+    },
+
+    Bound(Type),
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        let mut checker = Self {
+            scopes: vec![HashMap::new()],
+            variables: Vec::new(),
+            outputs: Vec::new(),
+            loops: Vec::new(),
+            type_parameters: Vec::new(),
+            interface_methods: HashMap::new(),
+            methods: HashMap::new(),
+            instances: HashSet::new(),
+        };
+
+        // The builtin interfaces, whose instances for builtin types are provided by the interpreter
+        for (name, methods) in interface::BUILTINS {
+            let interface = Interface::named(name);
+            let this = checker.self_variable(&interface);
+            let methods = methods
+                .iter()
+                .map(|method| {
+                    let ty = interface::builtin_method(method, &Type::Variable(this));
+                    (method.to_string(), ty)
+                })
+                .collect();
+            checker.declare_interface(interface, this, methods);
+        }
+
+        // The builtin functions provided by the interpreter
+        let value = checker.fresh();
+        let some = checker.generalize(&Type::Function(
+            vec![value.clone()],
+            Box::new(Type::option(value)),
+        ));
+        checker.bind_name("some", some);
+
+        checker
+    }
+
+    /// Check each statement of a program in the global scope, returning the type of the last
+    ///
+    /// If any statement is ill-typed, every error is returned and the global scope is left as it
+    /// was before the program.
+    pub fn check(&mut self, program: &Program) -> std::result::Result<Scheme, Vec<Error>> {
+        let globals = self.scopes[0].clone();
+        let declared = (
+            self.interface_methods.clone(),
+            self.methods.clone(),
+            self.instances.clone(),
+        );
+        let mut errors = Vec::new();
+        let mut ty = Type::named("Unit");
+
+        for statement in &program.0 {
+            match self.infer(statement) {
+                Ok(statement) => ty = statement,
+                Err(err) => {
+                    errors.push(err);
+
+                    // The name of a failed declaration may be used at any type, so that its uses
+                    // aren't reported as well
+                    if let Ast::Declaration { name, .. } = statement {
+                        let any = self.fresh();
+                        let scheme = self.generalize(&any);
+                        self.scopes[0].insert(name.clone(), scheme);
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(self.describe(&ty))
+        } else {
+            self.scopes[0] = globals;
+            (self.interface_methods, self.methods, self.instances) = declared;
+            Err(errors)
+        }
+    }
+
+    fn infer(&mut self, ast: &Ast) -> Result<Type> {
+        match ast {
+            // Nil is the absence of a value of any type
+            Ast::Literal(Literal::Nil, _) => {
+                let ty = self.fresh();
+                Ok(Type::option(ty))
+            }
+
+            Ast::Literal(lit, _) => Ok(Type::named(match lit {
+                Literal::Nil => unreachable!(),
+                Literal::Bool(_) => "Bool",
+                Literal::Integer(_) => "Integer",
+                Literal::Float(_) => "Float",
+                Literal::Str(_) => "String",
+            })),
+
+            Ast::Declaration {
+                name,
+                annotation,
+                value,
+                ..
+            } => {
+                // Functions may refer to themselves, as they are only run once declared
+                let recursive = if let Ast::Function { .. } = **value {
+                    let ty = self.fresh();
+                    self.bind_name(name, Scheme::monomorphic(ty.clone()));
+                    Some(ty)
+                } else {
+                    None
+                };
+
+                let ty = self.infer(value)?;
+                if let Some(recursive) = recursive {
+                    self.expect(&recursive, &ty, value.span())?;
+                    self.scopes.last_mut().unwrap().remove(name);
+                }
+                if let Some(annotation) = annotation {
+                    let annotation = self.annotated(annotation)?;
+                    self.expect(&annotation, &ty, value.span())?;
+                }
+
+                // Only functions are generalized, so other bindings may still be refined by
+                // later uses, like an assignment to a binding declared as `nil`
+                let scheme = if let Ast::Function { .. } = **value {
+                    self.generalize(&ty)
+                } else {
+                    Scheme::monomorphic(ty)
+                };
+                self.bind_name(name, scheme);
+                Ok(Type::named("Unit"))
+            }
+
+            Ast::Assignment { name, value, span } => {
+                let scheme = self.lookup(name, *span)?;
+                if !scheme.variables.is_empty() {
+                    return Err(Error::PolymorphicAssignment {
+                        name: name.clone(),
+                        span: *span,
+                    });
+                }
+
+                let ty = self.infer(value)?;
+                self.expect(&scheme.ty, &ty, value.span())?;
+                Ok(ty)
+            }
+
+            Ast::Reference(name, span) => {
+                let scheme = self.lookup(name, *span)?;
+                Ok(self.instantiate(&scheme))
+            }
+
+            Ast::Grouping(statements, tail, _) => self.scoped(HashMap::new(), |checker| {
+                for statement in statements {
+                    checker.infer(statement)?;
+                }
+
+                match tail {
+                    Some(tail) => checker.infer(tail),
+                    None => Ok(Type::named("Unit")),
+                }
+            }),
+
+            Ast::Operation(op, _) => match op {
+                Operation::Unary { operator, operand } => {
+                    let (parameters, output) = match operator.method() {
+                        Some(method) => self.method(method),
+                        None => (vec![Type::named("Bool")], Type::named("Bool")),
+                    };
+
+                    let operand_ty = self.infer(operand)?;
+                    self.expect(&parameters[0], &operand_ty, operand.span())?;
+
+                    Ok(output)
+                }
+
+                Operation::Binary { operator, operands } => {
+                    let (left, right) = &**operands;
+                    // Only ranges have operands of a fixed type
+                    let (parameters, output) = match operator.method() {
+                        Some(method) => self.method(method),
+                        None => (vec![Type::named("Integer"); 2], Type::named("Range")),
+                    };
+
+                    let left_ty = self.infer(left)?;
+                    self.expect(&parameters[0], &left_ty, left.span())?;
+                    let right_ty = self.infer(right)?;
+                    self.expect(&parameters[1], &right_ty, right.span())?;
+
+                    Ok(output)
+                }
+            },
+
+            Ast::Conditional {
+                binding,
+                condition,
+                consequence,
+                alternative,
+                span,
+            } => {
+                let condition_ty = self.infer(condition)?;
+                let ty = match binding {
+                    // The consequence is only run with the value of a present option
+                    Some(binding) => {
+                        let value = self.fresh();
+                        let option = Type::option(value.clone());
+                        self.expect(&option, &condition_ty, condition.span())?;
+
+                        let bindings = vec![(binding.clone(), Scheme::monomorphic(value))]
+                            .into_iter()
+                            .collect();
+                        self.scoped(bindings, |checker| checker.infer(consequence))?
+                    }
+                    None => {
+                        self.expect(&Type::named("Bool"), &condition_ty, condition.span())?;
+                        self.infer(consequence)?
+                    }
+                };
+
+                match alternative {
+                    Some(alternative) => {
+                        let alternative_ty = self.infer(alternative)?;
+                        self.expect(&ty, &alternative_ty, alternative.span())?;
+                    }
+                    None => {
+                        if self.unify(&Type::named("Unit"), &ty).is_err() {
+                            return Err(Error::MissingAlternative {
+                                found: self.normalize(&[&ty]).remove(0),
+                                span: *span,
+                            });
+                        }
+                    }
+                }
+
+                Ok(ty)
+            }
+
+            Ast::While {
+                label,
+                valued,
+                condition,
+                body,
+                ..
+            } => {
+                let condition_ty = self.infer(condition)?;
+                self.expect(&Type::named("Bool"), &condition_ty, condition.span())?;
+
+                self.looping(label, *valued, |checker| checker.infer(body))
+            }
+
+            Ast::For {
+                label,
+                valued,
+                binding,
+                iterable,
+                body,
+                ..
+            } => {
+                let iterable_ty = self.infer(iterable)?;
+
+                // Iterables of unknown type are taken to be ranges
+                let item = match self.shallow(&iterable_ty) {
+                    Type::Named(name, _) if name == "String" => Type::named("String"),
+                    ty if self.unify(&Type::named("Range"), &ty).is_ok() => Type::named("Integer"),
+                    _ => {
+                        return Err(Error::NotIterable {
+                            found: self.normalize(&[&iterable_ty]).remove(0),
+                            span: iterable.span(),
+                        })
+                    }
+                };
+
+                let bindings = vec![(binding.clone(), Scheme::monomorphic(item))]
+                    .into_iter()
+                    .collect();
+                self.looping(label, *valued, |checker| {
+                    checker.scoped(bindings, |checker| checker.infer(body))
+                })
+            }
+
+            Ast::Break { label, value, .. } => {
+                if let Some(value) = value {
+                    let ty = self.infer(value)?;
+                    let target = match label {
+                        Some(label) => self
+                            .loops
+                            .iter()
+                            .rev()
+                            .find(|(target, _)| target.as_ref() == Some(label)),
+                        None => self.loops.last(),
+                    };
+                    let (_, target) = target
+                        .cloned()
+                        .expect("the parser only allows breaks inside of loops");
+                    self.expect(&target, &ty, value.span())?;
+                }
+
+                // Jumps don't produce a value, so they fit in anywhere
+                Ok(self.fresh())
+            }
+
+            Ast::Continue { .. } => Ok(self.fresh()),
+
+            Ast::Return { value, span } => {
+                let output = self
+                    .outputs
+                    .last()
+                    .cloned()
+                    .expect("the parser only allows returns inside of functions");
+                match value {
+                    Some(value) => {
+                        let ty = self.infer(value)?;
+                        self.expect(&output, &ty, value.span())?;
+                    }
+                    None => self.expect(&output, &Type::named("Unit"), *span)?,
+                }
+
+                Ok(self.fresh())
+            }
+
+            Ast::Function {
+                type_parameters,
+                arguments,
+                output,
+                body,
+                ..
+            } => {
+                let mut rigid = Vec::new();
+                for param in type_parameters {
+                    let mut bounds = BTreeSet::new();
+                    for (bound, span) in &param.bounds {
+                        let interface = Interface::named(bound);
+                        if !self.interface_methods.contains_key(&interface) {
+                            return Err(Error::UnknownInterface {
+                                name: bound.clone(),
+                                span: *span,
+                            });
+                        }
+                        bounds.insert(interface);
+                    }
+                    rigid.push(Variable::Rigid {
+                        name: param.name.clone(),
+                        bounds,
+                    });
+                }
+
+                // Type parameters stand for any type the caller chooses, so they are rigid within
+                // the function
+                let scope = self.type_parameters.len();
+                for (param, var) in type_parameters.iter().zip(rigid) {
+                    self.variables.push(var);
+                    self.type_parameters
+                        .push((param.name.clone(), self.variables.len() - 1));
+                }
+                let ty = self.function(arguments, output, body);
+
+                // Outside of the function they are like any other variable, to be generalized
+                // together with their bounds
+                for (_, var) in self.type_parameters.split_off(scope) {
+                    if let Variable::Rigid { bounds, .. } = &mut self.variables[var] {
+                        let interfaces = std::mem::take(bounds);
+                        self.variables[var] = Variable::Unbound { interfaces };
+                    }
+                }
+                ty
+            }
+
+            Ast::FunctionApplication {
+                function,
+                arguments,
+                span,
+            } => {
+                let function_ty = self.infer(function)?;
+                let (parameters, output) = match self.shallow(&function_ty) {
+                    Type::Function(parameters, output) => (parameters, *output),
+                    ty @ Type::Variable(_) => {
+                        let parameters: Vec<_> = arguments.iter().map(|_| self.fresh()).collect();
+                        let output = self.fresh();
+                        let callable = Type::Function(parameters.clone(), Box::new(output.clone()));
+
+                        // Rigid and constrained variables can't be functions
+                        if self.unify(&ty, &callable).is_err() {
+                            return Err(Error::NotCallable {
+                                found: self.normalize(&[&ty]).remove(0),
+                                span: function.span(),
+                            });
+                        }
+
+                        (parameters, output)
+                    }
+                    ty => {
+                        return Err(Error::NotCallable {
+                            found: self.normalize(&[&ty]).remove(0),
+                            span: function.span(),
+                        })
+                    }
+                };
+
+                if parameters.len() != arguments.len() {
+                    return Err(Error::ArityMismatch {
+                        expected: parameters.len(),
+                        found: arguments.len(),
+                        span: *span,
+                    });
+                }
+                for (parameter, argument) in parameters.iter().zip(arguments) {
+                    let ty = self.infer(argument)?;
+                    self.expect(parameter, &ty, argument.span())?;
+                }
+
+                Ok(output)
+            }
+
+            Ast::Interface {
+                name,
+                methods,
+                span,
+            } => {
+                let interface = Interface::named(name);
+                if self.interface_methods.contains_key(&interface) {
+                    return Err(Error::DuplicateInterface {
+                        name: name.clone(),
+                        span: *span,
+                    });
+                }
+
+                // Self stands for the type implementing the interface in the signatures
+                let this = self.self_variable(&interface);
+                self.type_parameters.push(("Self".into(), this));
+                let signatures = self.signatures(this, methods);
+                self.type_parameters.pop();
+
+                self.declare_interface(interface, this, signatures?);
+                Ok(Type::named("Unit"))
+            }
+
+            Ast::Instance {
+                interface: name,
+                ty,
+                methods,
+                span,
+            } => {
+                let interface = Interface::named(name);
+                let declared =
+                    self.interface_methods
+                        .get(&interface)
+                        .cloned()
+                        .ok_or_else(|| Error::UnknownInterface {
+                            name: name.clone(),
+                            span: *span,
+                        })?;
+
+                // Methods are dispatched by the name of a type alone
+                let instance_ty = self.annotated(ty)?;
+                let type_name = match &instance_ty {
+                    Type::Named(type_name, arguments) if arguments.is_empty() => type_name.clone(),
+                    _ => {
+                        return Err(Error::InvalidInstance {
+                            found: self.normalize(&[&instance_ty]).remove(0),
+                            span: ty.span(),
+                        })
+                    }
+                };
+                if self.implements(&interface, &type_name) {
+                    return Err(Error::DuplicateInstance {
+                        interface,
+                        found: instance_ty,
+                        span: ty.span(),
+                    });
+                }
+
+                // The instance is declared before its methods are checked, so that they may use it
+                self.instances.insert((interface.clone(), type_name));
+                let mut implemented = Vec::new();
+                for method in methods {
+                    let (method_name, value, method_span) = match method {
+                        Ast::Declaration {
+                            name, value, span, ..
+                        } => (name, value, *span),
+                        _ => unreachable!("the parser only produces named methods"),
+                    };
+                    let scheme = match self.methods.get(method_name) {
+                        Some((declaring, scheme)) if *declaring == interface => scheme.clone(),
+                        _ => {
+                            return Err(Error::UnknownMethod {
+                                interface,
+                                name: method_name.clone(),
+                                span: method_span,
+                            })
+                        }
+                    };
+                    if implemented.contains(method_name) {
+                        return Err(Error::DuplicateMethod {
+                            name: method_name.clone(),
+                            span: method_span,
+                        });
+                    }
+                    implemented.push(method_name.clone());
+
+                    let (this, _) = scheme.variables[0];
+                    let expected = scheme.ty.substitute(&[(this, instance_ty.clone())]);
+                    let ty = self.infer(value)?;
+                    self.expect(&expected, &ty, value.span())?;
+                }
+
+                match declared
+                    .into_iter()
+                    .find(|name| !implemented.contains(name))
+                {
+                    Some(missing) => Err(Error::MissingMethod {
+                        interface,
+                        name: missing,
+                        span: *span,
+                    }),
+                    None => Ok(Type::named("Unit")),
+                }
+            }
+
+            Ast::Error => unreachable!("syntax errors are reported before type checking"),
+        }
+    }
+
+    fn function(
+        &mut self,
+        arguments: &[Parameter],
+        output: &Option<TypeExpr>,
+        body: &Ast,
+    ) -> Result<Type> {
+        let mut parameters = Vec::new();
+        for argument in arguments {
+            parameters.push(match &argument.annotation {
+                Some(annotation) => self.annotated(annotation)?,
+                None => self.fresh(),
+            });
+        }
+        let output = match output {
+            Some(output) => self.annotated(output)?,
+            None => self.fresh(),
+        };
+
+        let bindings = arguments
+            .iter()
+            .zip(&parameters)
+            .map(|(argument, ty)| (argument.name.clone(), Scheme::monomorphic(ty.clone())))
+            .collect();
+        // Loops can't be broken out of from inside a function
+        let loops = std::mem::take(&mut self.loops);
+        self.outputs.push(output.clone());
+        let body_ty = self.scoped(bindings, |checker| checker.infer(body));
+        self.outputs.pop();
+        self.loops = loops;
+        self.expect(&output, &body_ty?, body.span())?;
+
+        Ok(Type::Function(parameters, Box::new(output)))
+    }
+
+    // Interfaces
+
+    /// A fresh variable standing for the type implementing an interface
+    fn self_variable(&mut self, interface: &Interface) -> usize {
+        match self.constrained(interface.clone()) {
+            Type::Variable(var) => var,
+            _ => unreachable!(),
+        }
+    }
+
+    /// The types of the methods of an interface from their signatures
+    fn signatures(&mut self, this: usize, signatures: &[Signature]) -> Result<Vec<(String, Type)>> {
+        let mut methods = Vec::new();
+        for signature in signatures {
+            if self.methods.contains_key(&signature.name)
+                || methods.iter().any(|(name, _)| *name == signature.name)
+            {
+                return Err(Error::DuplicateMethod {
+                    name: signature.name.clone(),
+                    span: signature.span,
+                });
+            }
+
+            let mut parameters = Vec::new();
+            for parameter in &signature.parameters {
+                match &parameter.annotation {
+                    Some(annotation) => parameters.push(self.annotated(annotation)?),
+                    None => {
+                        return Err(Error::UnannotatedParameter {
+                            method: signature.name.clone(),
+                            name: parameter.name.clone(),
+                            span: signature.span,
+                        })
+                    }
+                }
+            }
+            if parameters.first() != Some(&Type::Variable(this)) {
+                return Err(Error::MethodWithoutSelf {
+                    name: signature.name.clone(),
+                    span: signature.span,
+                });
+            }
+            let output = match &signature.output {
+                Some(output) => self.annotated(output)?,
+                None => Type::named("Unit"),
+            };
+
+            methods.push((
+                signature.name.clone(),
+                Type::Function(parameters, Box::new(output)),
+            ));
+        }
+
+        Ok(methods)
+    }
+
+    /// Declare an interface with the types of its methods, which are generalized over the
+    /// variable standing for the type implementing it
+    ///
+    /// Methods are also bound as functions, applying the instance of the type they are applied to.
+    fn declare_interface(
+        &mut self,
+        interface: Interface,
+        this: usize,
+        methods: Vec<(String, Type)>,
+    ) {
+        let mut names = Vec::new();
+        for (name, ty) in methods {
+            let scheme = Scheme {
+                variables: vec![(this, self.interfaces(this))],
+                ty,
+            };
+            self.bind_name(&name, scheme.clone());
+            self.methods
+                .insert(name.clone(), (interface.clone(), scheme));
+            names.push(name);
+        }
+
+        self.interface_methods.insert(interface, names);
+    }
+
+    /// The parameter and output types of an instance of a builtin method, as applied by an
+    /// operator
+    fn method(&mut self, name: &str) -> (Vec<Type>, Type) {
+        let (_, scheme) = self.methods[name].clone();
+        match self.instantiate(&scheme) {
+            Type::Function(parameters, output) => (parameters, *output),
+            _ => unreachable!("methods are functions"),
+        }
+    }
+
+    /// Whether the named type has an instance of an interface, builtin or declared
+    fn implements(&self, interface: &Interface, name: &str) -> bool {
+        interface::builtin_instance(interface, name)
+            || self.instances.contains(&(interface.clone(), name.into()))
+    }
+
+    // Bindings
+
+    fn lookup(&self, name: &str, span: Span) -> Result<Scheme> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+            .ok_or_else(|| Error::UndefinedReference {
+                name: name.into(),
+                span,
+            })
+    }
+
+    fn bind_name(&mut self, name: &str, scheme: Scheme) {
+        self.scopes
+            .last_mut()
+            .expect("there is always a global scope")
+            .insert(name.into(), scheme);
+    }
+
+    /// Check the body of a loop, returning the type of the loop
+    ///
+    /// A valued loop is an option of the values it is broken out of with, as it may also finish
+    /// without breaking, and any other loop is unit.
+    fn looping(
+        &mut self,
+        label: &Option<String>,
+        valued: bool,
+        check: impl FnOnce(&mut Self) -> Result<Type>,
+    ) -> Result<Type> {
+        let value = self.fresh();
+        self.loops.push((label.clone(), value.clone()));
+        let res = check(self);
+        self.loops.pop();
+        res?;
+
+        Ok(if valued {
+            Type::option(value)
+        } else {
+            Type::named("Unit")
+        })
+    }
+
+    /// Check in a new innermost scope with some initial bindings, which is dropped afterwards
+    fn scoped<T>(
+        &mut self,
+        bindings: HashMap<String, Scheme>,
+        check: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        self.scopes.push(bindings);
+        let res = check(self);
+        self.scopes.pop();
+
+        res
+    }
+
+    /// The type written in an annotation
+    fn annotated(&mut self, annotation: &TypeExpr) -> Result<Type> {
+        match annotation {
+            TypeExpr::Named {
+                name,
+                arguments,
+                span,
+            } => {
+                let parameter = self
+                    .type_parameters
+                    .iter()
+                    .rev()
+                    .find(|(param, _)| param == name)
+                    .map(|(_, var)| *var);
+                let arity = match parameter {
+                    // Type parameters take no type arguments
+                    Some(_) => 0,
+                    None => match types::BUILTINS.iter().find(|(builtin, _)| builtin == name) {
+                        Some((_, arity)) => *arity,
+                        None => {
+                            return Err(Error::UnknownType {
+                                name: name.clone(),
+                                span: *span,
+                            })
+                        }
+                    },
+                };
+
+                if arguments.len() != arity {
+                    return Err(Error::TypeArity {
+                        name: name.clone(),
+                        expected: arity,
+                        found: arguments.len(),
+                        span: *span,
+                    });
+                }
+
+                Ok(match parameter {
+                    Some(var) => Type::Variable(var),
+                    None => Type::Named(
+                        name.clone(),
+                        arguments
+                            .iter()
+                            .map(|argument| self.annotated(argument))
+                            .collect::<Result<_>>()?,
+                    ),
+                })
+            }
+            TypeExpr::Function {
+                parameters, output, ..
+            } => Ok(Type::Function(
+                parameters
+                    .iter()
+                    .map(|parameter| self.annotated(parameter))
+                    .collect::<Result<_>>()?,
+                Box::new(match output {
+                    Some(output) => self.annotated(output)?,
+                    None => Type::named("Unit"),
+                }),
+            )),
+        }
+    }
+
+    // Type variables
+
+    fn fresh(&mut self) -> Type {
+        self.constrained_by(BTreeSet::new())
+    }
+
+    /// A fresh variable required to implement an interface
+    fn constrained(&mut self, interface: Interface) -> Type {
+        self.constrained_by(vec![interface].into_iter().collect())
+    }
+
+    fn constrained_by(&mut self, interfaces: BTreeSet<Interface>) -> Type {
+        self.variables.push(Variable::Unbound { interfaces });
+        Type::Variable(self.variables.len() - 1)
+    }
+
+    /// The interfaces an unbound variable must implement, or a type parameter is bounded by
+    fn interfaces(&self, var: usize) -> BTreeSet<Interface> {
+        match &self.variables[var] {
+            Variable::Unbound { interfaces } => interfaces.clone(),
+            Variable::Rigid { bounds, .. } => bounds.clone(),
+            Variable::Bound(_) => BTreeSet::new(),
+        }
+    }
+
+    /// Follow the bindings of a variable until reaching an unbound variable or another type
+    fn shallow(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Variable(var) => match &self.variables[*var] {
+                Variable::Bound(ty) => self.shallow(ty),
+                Variable::Unbound { .. } | Variable::Rigid { .. } => ty.clone(),
+            },
+            ty => ty.clone(),
+        }
+    }
+
+    /// Substitute every bound variable in a type
+    fn resolve(&self, ty: &Type) -> Type {
+        match self.shallow(ty) {
+            Type::Named(name, arguments) => Type::Named(
+                name,
+                arguments
+                    .iter()
+                    .map(|argument| self.resolve(argument))
+                    .collect(),
+            ),
+            Type::Function(parameters, output) => Type::Function(
+                parameters
+                    .iter()
+                    .map(|parameter| self.resolve(parameter))
+                    .collect(),
+                Box::new(self.resolve(&output)),
+            ),
+            ty => ty,
+        }
+    }
+
+    /// Resolve types to be shown together, renaming their unbound variables from the first
+    fn normalize(&self, types: &[&Type]) -> Vec<Type> {
+        let types: Vec<_> = types.iter().map(|ty| self.resolve(ty)).collect();
+
+        let mut variables = Vec::new();
+        for ty in &types {
+            ty.variables(&mut variables);
+        }
+        let mapping: Vec<_> = variables
+            .into_iter()
+            .enumerate()
+            .map(|(i, var)| match &self.variables[var] {
+                // Type parameters are shown by their names
+                Variable::Rigid { name, .. } => (var, Type::named(name)),
+                _ => (var, Type::Variable(i)),
+            })
+            .collect();
+
+        types.iter().map(|ty| ty.substitute(&mapping)).collect()
+    }
+
+    /// Resolve the type of a program to be shown, with the interfaces its variables implement
+    fn describe(&self, ty: &Type) -> Scheme {
+        let ty = self.resolve(ty);
+        let mut variables = Vec::new();
+        ty.variables(&mut variables);
+
+        let mapping: Vec<_> = variables
+            .iter()
+            .enumerate()
+            .map(|(i, var)| (*var, Type::Variable(i)))
+            .collect();
+        Scheme {
+            variables: variables
+                .iter()
+                .enumerate()
+                .map(|(i, var)| (i, self.interfaces(*var)))
+                .collect(),
+            ty: ty.substitute(&mapping),
+        }
+    }
+
+    /// Generalize a type over the variables which don't occur in any binding
+    fn generalize(&self, ty: &Type) -> Scheme {
+        let mut bound = Vec::new();
+        for scheme in self.scopes.iter().flat_map(HashMap::values) {
+            let mut variables = Vec::new();
+            self.resolve(&scheme.ty).variables(&mut variables);
+            bound.extend(variables.into_iter().filter(|var| {
+                !scheme
+                    .variables
+                    .iter()
+                    .any(|(generalized, _)| generalized == var)
+            }));
+        }
+
+        let ty = self.resolve(ty);
+        let mut variables = Vec::new();
+        ty.variables(&mut variables);
+
+        Scheme {
+            variables: variables
+                .into_iter()
+                .filter(|var| !bound.contains(var))
+                .map(|var| (var, self.interfaces(var)))
+                .collect(),
+            ty,
+        }
+    }
+
+    /// Replace the generalized variables of a scheme with fresh ones
+    fn instantiate(&mut self, scheme: &Scheme) -> Type {
+        let mapping: Vec<_> = scheme
+            .variables
+            .iter()
+            .map(|(var, interfaces)| (*var, self.constrained_by(interfaces.clone())))
+            .collect();
+
+        self.resolve(&scheme.ty).substitute(&mapping)
+    }
+
+    // Unification
+
+    /// Unify the type found for a syntax tree with the type expected of it
+    fn expect(&mut self, expected: &Type, found: &Type, span: Span) -> Result<()> {
+        match self.unify(expected, found) {
+            Ok(()) => Ok(()),
+            Err(Conflict::Mismatch) => {
+                let mut types = self.normalize(&[expected, found]);
+                Err(Error::Mismatch {
+                    found: types.remove(1),
+                    expected: types.remove(0),
+                    span,
+                })
+            }
+            Err(Conflict::Unimplemented(interface, ty)) => Err(Error::Unimplemented {
+                interface,
+                found: self.normalize(&[&ty]).remove(0),
+                span,
+            }),
+        }
+    }
+
+    /// Make two types equal by binding variables
+    fn unify(&mut self, a: &Type, b: &Type) -> std::result::Result<(), Conflict> {
+        match (self.shallow(a), self.shallow(b)) {
+            (Type::Variable(a), Type::Variable(b)) if a == b => Ok(()),
+            (Type::Variable(var), ty) | (ty, Type::Variable(var)) if !self.is_rigid(var) => {
+                self.bind(var, ty)
+            }
+            (Type::Named(a, a_arguments), Type::Named(b, b_arguments))
+                if a == b && a_arguments.len() == b_arguments.len() =>
+            {
+                for (a, b) in a_arguments.iter().zip(&b_arguments) {
+                    self.unify(a, b)?;
+                }
+                Ok(())
+            }
+            (Type::Function(a_parameters, a_output), Type::Function(b_parameters, b_output))
+                if a_parameters.len() == b_parameters.len() =>
+            {
+                for (a, b) in a_parameters.iter().zip(&b_parameters) {
+                    self.unify(a, b)?;
+                }
+                self.unify(&a_output, &b_output)
+            }
+            _ => Err(Conflict::Mismatch),
+        }
+    }
+
+    /// Bind an unbound variable to a type, unless it would be infinite or the type doesn't
+    /// implement the variable's interfaces
+    fn bind(&mut self, var: usize, ty: Type) -> std::result::Result<(), Conflict> {
+        let mut variables = Vec::new();
+        self.resolve(&ty).variables(&mut variables);
+        if variables.contains(&var) {
+            return Err(Conflict::Mismatch);
+        }
+
+        for interface in self.interfaces(var) {
+            self.require(interface, &ty)?;
+        }
+
+        self.variables[var] = Variable::Bound(ty);
+        Ok(())
+    }
+
+    /// Require a type to implement an interface, which is deferred for unbound variables
+    fn require(&mut self, interface: Interface, ty: &Type) -> std::result::Result<(), Conflict> {
+        let ty = self.shallow(ty);
+        match &ty {
+            Type::Variable(var) => match &mut self.variables[*var] {
+                Variable::Unbound { interfaces } => {
+                    interfaces.insert(interface);
+                    Ok(())
+                }
+                Variable::Rigid { bounds, .. } if bounds.contains(&interface) => Ok(()),
+                _ => Err(Conflict::Unimplemented(interface, ty)),
+            },
+            Type::Named(name, arguments) if self.implements(&interface, name) => {
+                for argument in arguments {
+                    if self.require(interface.clone(), argument).is_err() {
+                        return Err(Conflict::Unimplemented(interface, ty));
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(Conflict::Unimplemented(interface, ty)),
+        }
+    }
+
+    fn is_rigid(&self, var: usize) -> bool {
+        matches!(self.variables[var], Variable::Rigid { .. })
+    }
+}
+
+/// Why two types couldn't be unified
+enum Conflict {
+    Mismatch,
+
+    /// A type didn't implement an interface required of a variable it was unified with
+    Unimplemented(Interface, Type),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Analyzer;
+    use crate::parser::Parser;
+    use crate::source::SourceMap;
+
+    fn check(checker: &mut Checker, source: &str) -> std::result::Result<String, Vec<Error>> {
+        let file = SourceMap::new().add_repl_input(source.into());
+        let tokens = Analyzer::new(source.chars(), file).map(|tk| tk.unwrap());
+        let (program, errors) = Parser::new(tokens).parse();
+        assert_eq!(errors, [], "{}", source);
+
+        checker.check(&program).map(|ty| ty.to_string())
+    }
+
+    #[test]
+    fn infers_types() {
+        for (source, ty) in &[
+            ("", "Unit"),
+            ("1; 2.5", "Float"),
+            ("\"a\" == \"b\"", "Bool"),
+            ("-(1 + 2) * 3", "Integer"),
+            ("0..3", "Range"),
+            ("let a = 1", "Unit"),
+            ("let a = 1; { let a = true; a }", "Bool"),
+            ("if 1 < 2 { 1 } else if true { 2 } else { 3 }", "Integer"),
+            ("if true { let a = 1; }", "Unit"),
+            ("let s = 0; for i in 0..3 { s = s + i }; s", "Integer"),
+            ("for c in \"ab\" { c == \"a\" }", "Unit"),
+            ("while true { break }", "Unit"),
+            ("while true { break 1 }", "Option<Integer>"),
+            (
+                "for i in 0..3 { if i == 1 { break } else { break i } }",
+                "Option<Integer>",
+            ),
+            (
+                "'a: while true { while true { break 'a \"s\" }; break }",
+                "Option<String>",
+            ),
+            (
+                "while true { let f = || { while true { break 1 } }; break }",
+                "Unit",
+            ),
+            ("|x| x", "fn(a) -> a"),
+            ("|f, x| f(x)", "fn(fn(a) -> b, a) -> b"),
+            ("|x, y| x + y", "fn(a, a) -> a where a: Add"),
+            ("|x| x + 1.5", "fn(Float) -> Float"),
+            (
+                "fn f(x) { if x { return 1; }; 2 }; f",
+                "fn(Bool) -> Integer",
+            ),
+            (
+                "fn f(n) if n < 2 { 1 } else { n * f(n - 1) }; f",
+                "fn(Integer) -> Integer",
+            ),
+            ("let x: Float = 1.5; x", "Float"),
+            ("fn f(x: Integer) -> Bool x > 0; f", "fn(Integer) -> Bool"),
+            ("let g: fn(Integer) = |x| {}; g", "fn(Integer) -> Unit"),
+        ] {
+            assert_eq!(
+                check(&mut Checker::new(), source).as_deref(),
+                Ok(*ty),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn generalizes_declarations() {
+        for (source, ty) in &[
+            ("fn id(x) x; id(1); id(\"a\")", "String"),
+            (
+                "fn pair(x, y) |f| f(x, y); pair(1, true)",
+                "fn(fn(Integer, Bool) -> a) -> a",
+            ),
+            ("fn add(x, y) x + y; add(1, 2); add(1.5, 2.5)", "Float"),
+            ("|x| { let y = x; y + 1 }", "fn(Integer) -> Integer"),
+        ] {
+            assert_eq!(
+                check(&mut Checker::new(), source).as_deref(),
+                Ok(*ty),
+                "{}",
+                source
+            );
+        }
+
+        // Variables of the enclosing function are not generalized
+        assert!(check(
+            &mut Checker::new(),
+            "|x| { let y = || x; y() + 1; y() == true }"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn reports_type_errors() {
+        let errors = |source| check(&mut Checker::new(), source).unwrap_err();
+
+        for (source, range) in &[
+            ("1 + \"a\"", 4..7),
+            ("1 + 1.5", 4..7),
+            ("!1", 1..2),
+            ("let x: String = 1", 16..17),
+            ("if 1 { }", 3..4),
+            ("if true { 1 } else { \"a\" }", 19..20),
+            ("while 1 { }", 6..7),
+            ("fn f() -> Integer { return; }", 20..26),
+            ("|x| x(x)", 6..7),
+            ("(|x| x + 1)(true)", 12..16),
+            ("let id = |x| x; id(1) + id(true)", 26..27),
+        ] {
+            assert!(
+                matches!(&errors(source)[..], [err @ Error::Mismatch { .. }] if err.span().range() == *range),
+                "{}: {:?}",
+                source,
+                errors(source)
+            );
+        }
+
+        assert!(matches!(
+            &errors("x + 1")[..],
+            [Error::UndefinedReference { name, .. }] if name == "x"
+        ));
+        assert!(matches!(
+            &errors("let x: Text = \"a\"")[..],
+            [Error::UnknownType { name, .. }] if name == "Text"
+        ));
+        assert!(matches!(&errors("1(2)")[..], [Error::NotCallable { .. }]));
+        assert!(matches!(
+            &errors("(|x| x)(1, 2)")[..],
+            [Error::ArityMismatch {
+                expected: 1,
+                found: 2,
+                ..
+            }]
+        ));
+        assert!(matches!(
+            &errors("if true { 1 }")[..],
+            [Error::MissingAlternative { .. }]
+        ));
+        assert!(matches!(
+            &errors("fn id(x) x; id = |y| y")[..],
+            [Error::PolymorphicAssignment { .. }]
+        ));
+        assert!(matches!(
+            &errors("for x in 1 { }")[..],
+            [Error::NotIterable { .. }]
+        ));
+        assert!(matches!(
+            &errors("while true { break 1; break true }")[..],
+            [Error::Mismatch { .. }]
+        ));
+
+        // Failed declarations are not reported again when used
+        assert_eq!(errors("let a = 1 + true; a + 1; b").len(), 2);
+    }
+
+    #[test]
+    fn checks_type_parameters() {
+        for (source, ty) in &[
+            ("fn id<T>(x: T) -> T x; id(1); id(true)", "Bool"),
+            ("fn id<T>(x: T) -> T x; id", "fn(a) -> a"),
+            (
+                "fn<A, B>(f: fn(A) -> B, x: A) -> B f(x)",
+                "fn(fn(a) -> b, a) -> b",
+            ),
+            (
+                "fn f<T>(x: T) -> fn() -> T || { let y: T = x; y }; f(1)()",
+                "Integer",
+            ),
+            ("fn f<T>(x: T) -> T f(x); f", "fn(a) -> a"),
+            ("fn f<T>(x: T, y) -> T y; f", "fn(a, a) -> a"),
+        ] {
+            assert_eq!(
+                check(&mut Checker::new(), source).as_deref(),
+                Ok(*ty),
+                "{}",
+                source
+            );
+        }
+
+        let error = |source| {
+            let errors = check(&mut Checker::new(), source).unwrap_err();
+            assert_eq!(errors.len(), 1, "{}: {:?}", source, errors);
+            errors[0].to_string()
+        };
+
+        assert_eq!(
+            error("fn f<T>(x: T) -> T 1"),
+            "expected T but found Integer"
+        );
+        assert_eq!(error("fn f<T, U>(x: T) -> U x"), "expected U but found T");
+        assert_eq!(
+            error("fn f<T>(x: T) -> T x + x"),
+            "T does not implement Add"
+        );
+        assert_eq!(error("fn f<T>(x: T) {}; let y: T = 1"), "unknown type T");
+        assert_eq!(
+            error("let x: Integer<Bool> = 1"),
+            "Integer takes 0 type arguments but found 1"
+        );
+    }
+
+    #[test]
+    fn resolves_interfaces() {
+        for (source, ty) in &[
+            ("\"a\" + \"b\"", "String"),
+            ("\"a\" < \"b\"", "Bool"),
+            ("show(1) + show(0..2)", "String"),
+            ("show", "fn(a) -> String where a: Show"),
+            (
+                "|x, y| x < y == (x - y == y)",
+                "fn(a, a) -> Bool where a: Eq + Num + Ord",
+            ),
+            (
+                "fn f<T: Add + Show>(x: T) -> String show(x + x); f",
+                "fn(a) -> String where a: Add + Show",
+            ),
+            ("fn f<T: Num>(x: T) -> T -x; f(1.5)", "Float"),
+            ("let add = |x, y| x + y; add(\"a\", \"b\")", "String"),
+        ] {
+            assert_eq!(
+                check(&mut Checker::new(), source).as_deref(),
+                Ok(*ty),
+                "{}",
+                source
+            );
+        }
+
+        let error = |source| {
+            let errors = check(&mut Checker::new(), source).unwrap_err();
+            assert_eq!(errors.len(), 1, "{}: {:?}", source, errors);
+            errors[0].to_string()
+        };
+
+        assert_eq!(error("\"a\" - \"b\""), "String does not implement Num");
+        assert_eq!(error("true < false"), "Bool does not implement Ord");
+        assert_eq!(
+            error("|x| x == x; (|x| x) == (|x| x)"),
+            "fn(a) -> a does not implement Eq"
+        );
+        assert_eq!(
+            error("show(show)"),
+            "fn(a) -> String does not implement Show"
+        );
+        assert_eq!(
+            error("|x| { x + 1; x(1) }"),
+            "expected a function but found Integer"
+        );
+        assert_eq!(
+            error("fn f<T: Add>(x: T) x(1)"),
+            "expected a function but found T"
+        );
+        assert_eq!(error("fn f<T: Add>(x: T) -x"), "T does not implement Num");
+        assert_eq!(error("fn f<T: Hash>(x: T) {}"), "unknown interface Hash");
+        assert_eq!(
+            error("fn f<T: Eq>(x: T) x == x; f(|y| y)"),
+            "fn(a) -> a does not implement Eq"
+        );
+    }
+
+    #[test]
+    fn checks_options() {
+        for (source, ty) in &[
+            ("nil", "Option<a>"),
+            ("some(1)", "Option<Integer>"),
+            ("let x: Integer? = nil; x", "Option<Integer>"),
+            (
+                "let x: Option<Bool?> = some(nil); x",
+                "Option<Option<Bool>>",
+            ),
+            ("if let x = some(1) { x + 1 } else { 0 }", "Integer"),
+            ("if let x = nil { x; }", "Unit"),
+            (
+                "fn f(x: Integer?) -> Integer if let y = x { y } else { 0 }; f",
+                "fn(Option<Integer>) -> Integer",
+            ),
+            (
+                "|x| if let y = x { y } else { 1.5 }",
+                "fn(Option<Float>) -> Float",
+            ),
+            ("some(1) == nil", "Bool"),
+            ("show(some(\"a\"))", "String"),
+            (
+                "let best = nil; for i in 0..3 { best = some(i) }; best",
+                "Option<Integer>",
+            ),
+        ] {
+            assert_eq!(
+                check(&mut Checker::new(), source).as_deref(),
+                Ok(*ty),
+                "{}",
+                source
+            );
+        }
+
+        let error = |source| {
+            let errors = check(&mut Checker::new(), source).unwrap_err();
+            assert_eq!(errors.len(), 1, "{}: {:?}", source, errors);
+            errors[0].to_string()
+        };
+
+        assert_eq!(error("1 + nil"), "expected Integer but found Option<a>");
+        assert_eq!(
+            error("let x: Integer = nil"),
+            "expected Integer but found Option<a>"
+        );
+        assert_eq!(
+            error("if let x = 1 { }"),
+            "expected Option<a> but found Integer"
+        );
+        assert_eq!(
+            error("let x: Integer? = some(1); x + 1"),
+            "Option<Integer> does not implement Add"
+        );
+        assert_eq!(
+            error("if let x = some(1) { x }"),
+            "'if' without 'else' must be Unit, but its consequence is Integer"
+        );
+        assert_eq!(
+            error("let x: Option<Integer, Bool> = nil"),
+            "Option takes 1 type arguments but found 2"
+        );
+        assert_eq!(
+            error("some(|x| x) == nil"),
+            "Option<fn(a) -> a> does not implement Eq"
+        );
+    }
+
+    #[test]
+    fn checks_interfaces_and_instances() {
+        let describe = "interface Describe { fn describe(x: Self) -> String }
+            instance Describe for Integer { fn describe(n) \"integer \" + show(n) }
+            instance Describe for Bool { fn describe(b) if b { \"yes\" } else { \"no\" } }";
+
+        for (source, ty) in &[
+            (
+                "instance Add for Bool { fn add(a, b) if a { a } else { b } }; true + false",
+                "Bool",
+            ),
+            ("describe(1) + describe(true)", "String"),
+            ("describe", "fn(a) -> String where a: Describe"),
+            ("fn f<T: Describe>(x: T) describe(x); f(false)", "String"),
+            ("|x| describe(x) == describe(x + 1)", "fn(Integer) -> Bool"),
+            (
+                "interface Zero { fn zero(x: Self) -> Self; fn is_zero(x: Self) -> Bool }
+                instance Zero for Integer { fn zero(x) 0; fn is_zero(x) { x == 0 } }
+                is_zero",
+                "fn(a) -> Bool where a: Zero",
+            ),
+        ] {
+            let mut checker = Checker::new();
+            assert_eq!(check(&mut checker, describe).as_deref(), Ok("Unit"));
+            assert_eq!(
+                check(&mut checker, source).as_deref(),
+                Ok(*ty),
+                "{}",
+                source
+            );
+        }
+
+        let error = |source| {
+            let mut checker = Checker::new();
+            check(&mut checker, describe).unwrap();
+            let errors = check(&mut checker, source).unwrap_err();
+            assert_eq!(errors.len(), 1, "{}: {:?}", source, errors);
+            errors[0].to_string()
+        };
+
+        assert_eq!(
+            error("describe(\"a\")"),
+            "String does not implement Describe"
+        );
+        assert_eq!(
+            error("instance Hash for Integer {}"),
+            "unknown interface Hash"
+        );
+        assert_eq!(
+            error("interface Describe {}"),
+            "interface Describe is already declared"
+        );
+        assert_eq!(
+            error("interface Print { fn show(x: Self) -> String }"),
+            "method show is already declared"
+        );
+        assert_eq!(
+            error("interface Size { fn size(x: Self, y) -> Integer }"),
+            "parameter y of method size must be annotated"
+        );
+        assert_eq!(
+            error("interface Default { fn default() -> Self }"),
+            "the first parameter of method default must be of type Self"
+        );
+        assert_eq!(
+            error("instance Eq for Integer { fn eq(a, b) true }"),
+            "Integer already implements Eq"
+        );
+        assert_eq!(
+            error("instance Describe for Option<Integer> { fn describe(x) \"\" }"),
+            "instances can only be declared for named types without type arguments, not \
+             Option<Integer>"
+        );
+        assert_eq!(
+            error("instance Describe for String {}"),
+            "instance of Describe is missing method describe"
+        );
+        assert_eq!(
+            error("instance Describe for String { fn describe(s) s; fn size(s) 1 }"),
+            "size is not a method of Describe"
+        );
+        assert_eq!(
+            error("instance Describe for String { fn describe(s) 1 }"),
+            "expected fn(String) -> String but found fn(String) -> Integer"
+        );
+
+        // The declarations of a program which fails to check are forgotten
+        let mut checker = Checker::new();
+        assert!(check(
+            &mut checker,
+            "interface Size { fn size(x: Self) -> Integer }; 1 + true"
+        )
+        .is_err());
+        assert_eq!(
+            check(&mut checker, "interface Size { fn size(x: Self) -> Bool }").as_deref(),
+            Ok("Unit")
+        );
+    }
+
+    #[test]
+    fn checks_closures_against_earlier_declarations() {
+        for (source, ty) in &[
+            ("let a = 1; { fn g() a; let a = true; g() + 1 }", "Integer"),
+            ("let x = 1; fn f() x + 1; let x = \"a\"; f()", "Integer"),
+            (
+                "let x = 1; fn f() x; let x = \"a\"; x + show(f())",
+                "String",
+            ),
+        ] {
+            assert_eq!(
+                check(&mut Checker::new(), source).as_deref(),
+                Ok(*ty),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn declarations_persist_between_programs() {
+        let mut checker = Checker::new();
+
+        assert_eq!(check(&mut checker, "let a = 1").as_deref(), Ok("Unit"));
+        assert!(check(&mut checker, "let b = 2; a + \"s\"").is_err());
+        assert!(check(&mut checker, "b").is_err());
+        assert_eq!(check(&mut checker, "a").as_deref(), Ok("Integer"));
+    }
+}
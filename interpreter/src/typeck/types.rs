@@ -0,0 +1,171 @@
+use super::Interface;
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+
+/// The names of the builtin types, which may be used in annotations, with the number of type
+/// arguments they take
+pub const BUILTINS: &[(&str, usize)] = &[
+    ("Unit", 0),
+    ("Bool", 0),
+    ("Integer", 0),
+    ("Float", 0),
+    ("String", 0),
+    ("Range", 0),
+    ("Option", 1),
+];
+
+/// A Smoke type
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    /// A type which is yet to be inferred
+    Variable(usize),
+
+    /// A builtin type applied to its type arguments, such as `Integer`
+    Named(String, Vec<Type>),
+
+    /// The type of functions from their argument types to their output type
+    Function(Vec<Type>, Box<Type>),
+}
+
+impl Type {
+    pub fn named(name: &str) -> Self {
+        Self::Named(name.into(), Vec::new())
+    }
+
+    /// The type of an optional value, which is either nil or a value of the given type
+    pub fn option(ty: Self) -> Self {
+        Self::Named("Option".into(), vec![ty])
+    }
+
+    /// Collect the variables occurring in the type, in order of first occurrence
+    pub fn variables(&self, variables: &mut Vec<usize>) {
+        match self {
+            Self::Variable(var) => {
+                if !variables.contains(var) {
+                    variables.push(*var);
+                }
+            }
+            Self::Named(_, arguments) => {
+                for argument in arguments {
+                    argument.variables(variables);
+                }
+            }
+            Self::Function(parameters, output) => {
+                for parameter in parameters {
+                    parameter.variables(variables);
+                }
+                output.variables(variables);
+            }
+        }
+    }
+
+    /// Replace variables according to a mapping, leaving unmapped variables as they are
+    pub fn substitute(&self, mapping: &[(usize, Type)]) -> Self {
+        match self {
+            Self::Variable(var) => mapping
+                .iter()
+                .find(|(from, _)| from == var)
+                .map_or_else(|| self.clone(), |(_, to)| to.clone()),
+            Self::Named(name, arguments) => Self::Named(
+                name.clone(),
+                arguments
+                    .iter()
+                    .map(|argument| argument.substitute(mapping))
+                    .collect(),
+            ),
+            Self::Function(parameters, output) => Self::Function(
+                parameters
+                    .iter()
+                    .map(|parameter| parameter.substitute(mapping))
+                    .collect(),
+                Box::new(output.substitute(mapping)),
+            ),
+        }
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            // Variables are named a to z, then a1 to z1 and so on
+            Self::Variable(var) => {
+                write!(f, "{}", (b'a' + (var % 26) as u8) as char)?;
+                if *var >= 26 {
+                    write!(f, "{}", var / 26)?;
+                }
+                Ok(())
+            }
+            Self::Named(name, arguments) => {
+                write!(f, "{}", name)?;
+                if !arguments.is_empty() {
+                    write!(f, "<")?;
+                    write_list(f, arguments)?;
+                    write!(f, ">")?;
+                }
+                Ok(())
+            }
+            Self::Function(parameters, output) => {
+                write!(f, "fn(")?;
+                write_list(f, parameters)?;
+                write!(f, ") -> {}", output)
+            }
+        }
+    }
+}
+
+/// Write types separated by commas
+fn write_list(f: &mut Formatter, types: &[Type]) -> fmt::Result {
+    for (i, ty) in types.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", ty)?;
+    }
+    Ok(())
+}
+
+/// A type generalized over some of its variables, which are replaced afresh at each use
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scheme {
+    /// The generalized variables, with the interfaces they must implement
+    pub variables: Vec<(usize, BTreeSet<Interface>)>,
+    pub ty: Type,
+}
+
+impl Scheme {
+    pub fn monomorphic(ty: Type) -> Self {
+        Self {
+            variables: Vec::new(),
+            ty,
+        }
+    }
+}
+
+impl Display for Scheme {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.ty)?;
+
+        // Interfaces are listed in a where clause, as in `fn(a, a) -> a where a: Add`
+        let mut constrained = self
+            .variables
+            .iter()
+            .filter(|(_, interfaces)| !interfaces.is_empty());
+        if let Some(first) = constrained.next() {
+            write!(f, " where ")?;
+            for (i, (var, interfaces)) in std::iter::once(first).chain(constrained).enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}: ", Type::Variable(*var))?;
+                for (j, interface) in interfaces.iter().enumerate() {
+                    if j > 0 {
+                        write!(f, " + ")?;
+                    }
+                    write!(f, "{}", interface)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
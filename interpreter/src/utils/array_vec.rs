@@ -11,7 +11,7 @@ pub struct ArrayVec<T, const N: usize> {
 impl<T, const N: usize> ArrayVec<T, N> {
     pub const fn new() -> Self {
         Self {
-            inner: MaybeUninit::uninit_array(),
+            inner: [const { MaybeUninit::uninit() }; N],
             length: 0,
         }
     }
@@ -35,7 +35,7 @@ impl<T, const N: usize> ArrayVec<T, N> {
 impl<T, const N: usize> Deref for ArrayVec<T, N> {
     type Target = [T];
     fn deref(&self) -> &Self::Target {
-        unsafe { MaybeUninit::slice_assume_init_ref(&self.inner[..self.length]) }
+        unsafe { self.inner[..self.length].assume_init_ref() }
     }
 }
 
@@ -1,50 +1,116 @@
 use super::array_vec::ArrayVec;
+use std::cell::Cell;
 use std::io::{self, Read};
-use std::iter::FilterMap;
+use std::iter::Peekable;
+use std::rc::Rc;
 
+/// An iterator decoding UTF-8 bytes into characters
+///
+/// Invalid sequences are decoded as [`char::REPLACEMENT_CHARACTER`].
 #[derive(Debug, Clone)]
 pub struct CharIter<I: Iterator<Item = u8>> {
-    iter: I,
+    iter: Peekable<I>,
 }
 
 impl<I: Iterator<Item = u8>> CharIter<I> {
     pub fn new(iter: I) -> Self {
-        Self { iter }
+        Self {
+            iter: iter.peekable(),
+        }
     }
 }
 
 impl<I: Iterator<Item = u8>> Iterator for CharIter<I> {
     type Item = char;
     fn next(&mut self) -> Option<Self::Item> {
+        let first = self.iter.next()?;
+        let length = match first.leading_ones() {
+            0 => return Some(first.into()),
+            len @ 2..=4 => len as usize,
+            _ => return Some(char::REPLACEMENT_CHARACTER),
+        };
+
         let mut buf = ArrayVec::<u8, 4>::new();
-        while !buf.is_full() {
-            if let Some(byte) = self.iter.next() {
-                buf.push(byte);
-                if let Ok(st) = std::str::from_utf8(&buf) {
-                    return st.chars().next();
-                }
-            } else {
-                break;
+        buf.push(first);
+        while buf.len() < length && !buf.is_full() {
+            match self.iter.next_if(|byte| byte & 0b1100_0000 == 0b1000_0000) {
+                Some(byte) => buf.push(byte),
+                None => break,
             }
         }
-        None
+
+        Some(
+            std::str::from_utf8(&buf)
+                .ok()
+                .and_then(|st| st.chars().next())
+                .unwrap_or(char::REPLACEMENT_CHARACTER),
+        )
     }
 }
 
 pub trait IntoCharIter {
-    type IntoIter: Iterator<Item = char>;
+    type IntoIter: Iterator<Item = io::Result<char>>;
     fn chars(self) -> Self::IntoIter;
 }
 
-impl<'a> IntoCharIter for io::StdinLock<'a> {
-    type IntoIter = CharIter<
-        FilterMap<
-            std::io::Bytes<io::StdinLock<'a>>,
-            fn(std::result::Result<u8, std::io::Error>) -> Option<u8>,
-        >,
-    >;
+/// Readers are decoded as UTF-8, ending with the first error
+///
+/// Reading is done byte by byte, so unbuffered readers should be wrapped in a
+/// [`BufReader`](io::BufReader).
+impl<R: Read> IntoCharIter for R {
+    type IntoIter = ReadChars<R>;
+    #[allow(clippy::unbuffered_bytes)]
     fn chars(self) -> Self::IntoIter {
-        CharIter::new(self.bytes().filter_map(Result::ok))
+        let error = Rc::new(Cell::new(None));
+
+        ReadChars {
+            chars: CharIter::new(ReadBytes {
+                bytes: Some(self.bytes()),
+                error: Rc::clone(&error),
+            }),
+            error,
+        }
+    }
+}
+
+/// The characters of a reader, or the error which ended them
+pub struct ReadChars<R: Read> {
+    chars: CharIter<ReadBytes<R>>,
+
+    /// The error which ended the bytes, shared with them as the [`CharIter`] owns them
+    error: Rc<Cell<Option<io::Error>>>,
+}
+
+impl<R: Read> Iterator for ReadChars<R> {
+    type Item = io::Result<char>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let ch = self.chars.next();
+
+        // A character cut short by an error is dropped
+        match self.error.take() {
+            Some(err) => Some(Err(err)),
+            None => ch.map(Ok),
+        }
+    }
+}
+
+/// The bytes of a reader, which end at the first error
+struct ReadBytes<R> {
+    bytes: Option<io::Bytes<R>>,
+    error: Rc<Cell<Option<io::Error>>>,
+}
+
+impl<R: Read> Iterator for ReadBytes<R> {
+    type Item = u8;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.bytes.as_mut()?.next()? {
+            Ok(byte) => Some(byte),
+            Err(err) => {
+                self.bytes = None;
+                self.error.set(Some(err));
+                None
+            }
+        }
     }
 }
 
@@ -61,4 +127,54 @@ mod tests {
 
         assert!(from_bytes.eq(correct));
     }
+
+    #[test]
+    fn char_iter_decodes_multibyte() {
+        let source = "ü, 日本語, 🦀";
+
+        assert!(CharIter::new(source.bytes()).eq(source.chars()));
+        assert!(source
+            .as_bytes()
+            .chars()
+            .map(Result::unwrap)
+            .eq(source.chars()));
+    }
+
+    #[test]
+    fn char_iter_replaces_invalid_sequences() {
+        let bytes = b"a\xffb\xe6\x97c\xf0\x9f\xa6\x80";
+
+        assert_eq!(
+            CharIter::new(bytes.iter().copied()).collect::<String>(),
+            String::from_utf8_lossy(bytes),
+        );
+    }
+
+    #[test]
+    fn read_chars_end_with_errors() {
+        /// A reader which fails after its content
+        struct Failing<'a>(&'a [u8]);
+
+        impl Read for Failing<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match self.0.read(buf)? {
+                    0 => Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed")),
+                    read => Ok(read),
+                }
+            }
+        }
+
+        let mut chars = Failing("aü".as_bytes()).chars();
+        assert_eq!(chars.next().unwrap().unwrap(), 'a');
+        assert_eq!(chars.next().unwrap().unwrap(), 'ü');
+        assert_eq!(
+            chars.next().unwrap().unwrap_err().kind(),
+            io::ErrorKind::BrokenPipe
+        );
+        assert!(chars.next().is_none());
+
+        // The character being decoded when the error occurred is dropped
+        let mut chars = Failing(&"ü".as_bytes()[..1]).chars();
+        assert!(chars.next().unwrap().is_err());
+    }
 }